@@ -0,0 +1,362 @@
+use crate::board::Board;
+use crate::game::{Input, TetrisGame};
+use crate::rotation::Rotation;
+use crate::tetris::{Position, Tetris};
+
+/// Ticks between two inputs of [`Ai::paced_input`], slow enough to follow what the AI does
+pub const AI_INPUT_DELAY: u64 = 4;
+
+/// How much each board feature counts when the AI compares placements
+///
+/// Positive weights are wanted, negative ones are avoided.
+/// The defaults are Pierre Dellacherie's, with small extra weights on height and bumpiness
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AiWeights {
+    /// Height the tetris lands at, from the floor
+    pub landing_height: f64,
+    /// Lines cleared times the squares of the tetris that were in them
+    pub eroded_cells: f64,
+    /// Lines cleared by the placement
+    pub lines_cleared: f64,
+    /// Filled and empty squares next to each other in a row, walls count as filled
+    pub row_transitions: f64,
+    /// Filled and empty squares next to each other in a column, the floor counts as filled
+    pub column_transitions: f64,
+    /// Empty squares with a filled square somewhere above them
+    pub holes: f64,
+    /// Sum of the depths of the wells, a well of depth 3 counts 1 + 2 + 3
+    pub wells: f64,
+    /// Sum of the column heights
+    pub aggregate_height: f64,
+    /// Sum of the height differences between neighbour columns
+    pub bumpiness: f64
+}
+
+impl Default for AiWeights {
+    fn default() -> Self {
+        Self {
+            landing_height: -1.0,
+            eroded_cells: 1.0,
+            lines_cleared: 0.0,
+            row_transitions: -1.0,
+            column_transitions: -1.0,
+            holes: -4.0,
+            wells: -1.0,
+            aggregate_height: -0.1,
+            bumpiness: -0.1
+        }
+    }
+}
+
+/// Where the AI wants current_tetris to go
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Target {
+    rotation: Rotation,
+    /// Board column of the leftmost square, kicks can move the position but not the shape
+    left_column: i32
+}
+
+/// Computer player
+///
+/// For each new tetris it tries every rotation and column reachable from where the tetris is,
+/// drops it, scores the resulting board with [`AiWeights`] and then gives the inputs to get to the best one
+pub struct Ai {
+    weights: AiWeights,
+    target: Option<Target>,
+    /// Number of tetris locked when the target was picked, a new tetris needs a new target
+    planned_for: usize,
+    /// Inputs given to the current tetris, it gets dropped if it can't reach its target
+    inputs_given: usize
+}
+
+impl Default for Ai {
+    fn default() -> Self {
+        Self::new(AiWeights::default())
+    }
+}
+
+impl Ai {
+    pub fn new(weights: AiWeights) -> Self {
+        Self {
+            weights,
+            target: None,
+            planned_for: usize::MAX,
+            inputs_given: 0
+        }
+    }
+
+    /// Input for the current tick when the AI plays one every [`AI_INPUT_DELAY`] ticks, call it once per tick.
+    /// The game window and tetris-sim both pace the AI this way so a seed plays the same game in both
+    pub fn paced_input(&mut self, game: &TetrisGame) -> Option<Input> {
+        if !game.ticks().is_multiple_of(AI_INPUT_DELAY) {
+            return None;
+        }
+
+        self.next_input(game)
+    }
+
+    /// Next input to give to the game, None once it is over
+    ///
+    /// Gives one input per call: rotations first, then moves, then a hard drop
+    pub fn next_input(&mut self, game: &TetrisGame) -> Option<Input> {
+        if game.is_gameover() {
+            return None;
+        }
+
+        let pieces = game.stats().pieces;
+        if pieces != self.planned_for {
+            self.target = self.best_target(game);
+            self.planned_for = pieces;
+            self.inputs_given = 0;
+        }
+
+        self.inputs_given += 1;
+        let Some(target) = self.target else {
+            return Some(Input::HardDrop);
+        };
+
+        // A blocked move or rotation would be retried forever
+        if self.inputs_given > 2 * game.board().width() + 8 {
+            return Some(Input::HardDrop);
+        }
+
+        let tetris = game.current_tetris();
+        if tetris.rotation != target.rotation {
+            return Some(if tetris.rotation.counter_clockwise() == target.rotation { Input::RotateLeft } else { Input::RotateRight });
+        }
+
+        let left_column = tetris.position.x + tetris.most_left().unwrap_or_default() as i32;
+        Some(match target.left_column.cmp(&left_column) {
+            std::cmp::Ordering::Less => Input::MoveLeft,
+            std::cmp::Ordering::Greater => Input::MoveRight,
+            std::cmp::Ordering::Equal => Input::HardDrop
+        })
+    }
+
+    /// Tries every rotation and column, None if the tetris can't go anywhere
+    fn best_target(&self, game: &TetrisGame) -> Option<Target> {
+        let current = *game.current_tetris();
+        let mut rotated = current;
+        let mut best: Option<(f64, Target)> = None;
+
+        for _ in 0..4 {
+            // Rotation is assumed to happen in place, skip it if it can't
+            if game.fits(&rotated) {
+                for tetris in Self::reachable_columns(game, &rotated) {
+                    let score = self.score_placement(game, &tetris);
+                    if best.is_none_or(|(best_score, _)| score > best_score) {
+                        let target = Target {
+                            rotation: tetris.rotation,
+                            left_column: tetris.position.x + tetris.most_left().unwrap_or_default() as i32
+                        };
+                        best = Some((score, target));
+                    }
+                }
+            }
+
+            rotated.map = rotated.rotate_right_result();
+            rotated.rotation = rotated.rotation.clockwise();
+        }
+
+        best.map(|(_, target)| target)
+    }
+
+    /// Every position the tetris can be slid to from where it is, without going down
+    fn reachable_columns(game: &TetrisGame, tetris: &Tetris) -> Vec<Tetris> {
+        let mut reachable = vec![*tetris];
+        for direction in [-1, 1] {
+            let mut moved = *tetris;
+            loop {
+                moved.position.x += direction;
+                if !game.fits(&moved) {
+                    break;
+                }
+                reachable.push(moved);
+            }
+        }
+
+        reachable
+    }
+
+    /// Drops the tetris, places it on a copy of the board and scores the result
+    fn score_placement(&self, game: &TetrisGame, tetris: &Tetris) -> f64 {
+        let mut dropped = *tetris;
+        while game.fits(&Tetris { position: Position { x: dropped.position.x, y: dropped.position.y + 1 }, ..dropped }) {
+            dropped.position.y += 1;
+        }
+
+        let mut board = game.board().clone();
+        let height = board.height() as i32;
+        let mut squares = Vec::new();
+        for (yindex, row) in dropped.map.iter().enumerate() {
+            for (xindex, filled) in row.iter().enumerate() {
+                let x = dropped.position.x + xindex as i32;
+                let y = dropped.position.y + yindex as i32;
+                if *filled && y >= 0 {
+                    board[y as usize][x as usize] = Some(dropped.tetris_type);
+                    squares.push(y);
+                }
+            }
+        }
+
+        // Landing height is taken at the middle of the tetris
+        let landing_height = squares.iter().map(|y| (height - y) as f64).sum::<f64>() / squares.len().max(1) as f64 - 0.5;
+
+        let mut lines_cleared = 0;
+        let mut cleared_squares = 0;
+        for y in 0..board.height() {
+            if board.is_line_full(y) {
+                lines_cleared += 1;
+                cleared_squares += squares.iter().filter(|square| **square == y as i32).count();
+                board.clear_line(y);
+            }
+        }
+
+        let features = BoardFeatures::of(&board);
+        let weights = &self.weights;
+
+        weights.landing_height * landing_height +
+            weights.eroded_cells * (lines_cleared * cleared_squares) as f64 +
+            weights.lines_cleared * lines_cleared as f64 +
+            weights.row_transitions * features.row_transitions as f64 +
+            weights.column_transitions * features.column_transitions as f64 +
+            weights.holes * features.holes as f64 +
+            weights.wells * features.wells as f64 +
+            weights.aggregate_height * features.aggregate_height as f64 +
+            weights.bumpiness * features.bumpiness as f64
+    }
+}
+
+/// Board measures the AI scores, see [`AiWeights`]
+#[derive(Default)]
+struct BoardFeatures {
+    row_transitions: usize,
+    column_transitions: usize,
+    holes: usize,
+    wells: usize,
+    aggregate_height: usize,
+    bumpiness: usize
+}
+
+impl BoardFeatures {
+    fn of(board: &Board) -> Self {
+        let mut features = BoardFeatures::default();
+        let filled = |x: i32, y: usize| board.is_blocked(x, y as i32);
+
+        for y in 0..board.height() {
+            // Walls count as filled, so an empty row still has 2 transitions
+            for x in 0..=board.width() as i32 {
+                if filled(x - 1, y) != filled(x, y) {
+                    features.row_transitions += 1;
+                }
+            }
+        }
+
+        let mut heights = Vec::with_capacity(board.width());
+        for x in 0..board.width() as i32 {
+            let top = (0..board.height()).find(|y| filled(x, *y)).unwrap_or(board.height());
+            heights.push(board.height() - top);
+
+            // Floor counts as filled
+            for y in 0..board.height() {
+                if filled(x, y) != filled(x, y + 1) {
+                    features.column_transitions += 1;
+                }
+            }
+
+            let mut depth = 0;
+            for y in 0..board.height() {
+                if !filled(x, y) && filled(x - 1, y) && filled(x + 1, y) {
+                    depth += 1;
+                    features.wells += depth;
+                } else {
+                    depth = 0;
+                }
+            }
+        }
+
+        features.holes = board.holes();
+        features.aggregate_height = heights.iter().sum();
+        features.bumpiness = heights.windows(2).map(|pair| pair[0].abs_diff(pair[1])).sum();
+        features
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{GameEvent, GameSettings};
+
+    use super::*;
+
+    /// Lets the AI play until it placed `pieces` tetris or the game ended
+    fn play(seed: u64, pieces: usize) -> TetrisGame {
+        let mut game = TetrisGame::with_settings(seed, GameSettings::default());
+        let mut ai = Ai::default();
+
+        while !game.is_gameover() && game.stats().pieces < pieces {
+            // A tetris never needs more inputs than that, the AI drops it when stuck
+            for _ in 0..64 {
+                let Some(input) = ai.next_input(&game) else { break };
+                game.handle_input(input);
+                if input == Input::HardDrop {
+                    break;
+                }
+            }
+            game.tick();
+            game.drain_events();
+        }
+
+        game
+    }
+
+    #[test]
+    fn clears_lines_without_topping_out() {
+        for seed in [1, 2, 3] {
+            let game = play(seed, 200);
+            assert!(!game.is_gameover(), "seed {} topped out after {} tetris", seed, game.stats().pieces);
+            assert!(game.lines() >= 60, "seed {} only cleared {} lines", seed, game.lines());
+            assert!(game.board().holes() < 5);
+        }
+    }
+
+    #[test]
+    fn paced_ai_gives_one_input_every_few_ticks() {
+        let mut game = TetrisGame::with_settings(6, GameSettings::default());
+        let mut ai = Ai::default();
+
+        while game.ticks() < 6000 && !game.is_gameover() {
+            let input = ai.paced_input(&game);
+            assert!(input.is_none() || game.ticks().is_multiple_of(AI_INPUT_DELAY));
+            if let Some(input) = input {
+                game.handle_input(input);
+            }
+            game.tick();
+        }
+
+        assert!(!game.is_gameover());
+        assert!(game.lines() > 0);
+    }
+
+    #[test]
+    fn plays_the_same_game_for_a_seed() {
+        assert_eq!(play(9, 50).stats(), play(9, 50).stats());
+    }
+
+    #[test]
+    fn drops_the_tetris_within_one_tick_of_inputs() {
+        let mut game = TetrisGame::new(4);
+        let mut ai = Ai::default();
+
+        let mut inputs = 0;
+        while let Some(input) = ai.next_input(&game) {
+            game.handle_input(input);
+            inputs += 1;
+            if input == Input::HardDrop {
+                break;
+            }
+            assert!(inputs < 64, "the AI never dropped its tetris");
+        }
+
+        assert!(game.drain_events().contains(&GameEvent::PieceLocked));
+    }
+}
@@ -0,0 +1,42 @@
+use std::str::FromStr;
+
+/// Parses the value given to a command line option, the error names both
+///
+/// Shared by the command lines of the game and of tetris-sim
+pub fn parse_value<T: FromStr>(name: &str, value: &str) -> Result<T, String>
+where
+    T::Err: ToString
+{
+    value.parse().map_err(|e: T::Err| format!("invalid value \"{}\" for {}: {}", value, name, e.to_string()))
+}
+
+/// Splits `--seed=12` into the name and its value. `--seed` alone has no value, the next argument is the value
+pub fn split_option(arg: String) -> (String, Option<String>) {
+    match arg.split_once('=') {
+        Some((name, value)) => (name.to_string(), Some(value.to_string())),
+        None => (arg, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GameMode;
+
+    use super::*;
+
+    #[test]
+    fn errors_name_the_option() {
+        assert_eq!(parse_value::<u64>("--seed", "12"), Ok(12));
+        assert_eq!(parse_value::<GameMode>("--mode", "Sprint"), Ok(GameMode::Sprint));
+
+        let error = parse_value::<u64>("--seed", "twelve").unwrap_err();
+        assert!(error.contains("--seed") && error.contains("twelve"), "{}", error);
+    }
+
+    #[test]
+    fn values_can_follow_an_equal_sign() {
+        assert_eq!(split_option("--seed=12".to_string()), ("--seed".to_string(), Some("12".to_string())));
+        assert_eq!(split_option("--join=a=b".to_string()), ("--join".to_string(), Some("a=b".to_string())));
+        assert_eq!(split_option("--ai".to_string()), ("--ai".to_string(), None));
+    }
+}
@@ -0,0 +1,257 @@
+//! Runs the game logic without a window, as fast as possible, then prints how the game went
+//!
+//! Uses the same [`TetrisGame`] as the SDL front-end so the results match it tick for tick.
+//! The game is driven by a replay file or by the built-in [`Ai`].
+//! With `--host` or `--join` the AI plays a network versus match, two processes on one machine test the netcode
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::net::TcpListener;
+use std::time::Instant;
+
+use tetris_sdl_rs::args::{parse_value, split_option};
+use tetris_sdl_rs::netplay::{state_hash, INPUT_DELAY};
+use tetris_sdl_rs::{
+    Ai, GameMode, GameSettings, GameStats, NetRole, NetSession, Replay, ReplayPlayer, TetrisGame, VersusResult,
+    PLAYERS, TICKS_PER_SECOND
+};
+
+/// Games longer than this are stopped, one hour of play
+const DEFAULT_MAX_TICKS: u64 = 60 * 60 * TICKS_PER_SECOND as u64;
+
+const USAGE: &str = "Usage: tetris-sim (--script <FILE> | --ai) [OPTIONS]
+
+Options:
+  --script <FILE>     Replay file giving the seed, settings and inputs by tick
+  --ai                Lets the built-in AI play
+  --seed <N>          Seed of the AI game, random by default
+  --mode <M>          Mode of the AI game: marathon, sprint or ultra
+  --host <PORT>       With --ai, waits for a player on the port and plays a versus match over the network
+  --join <ADDR>       With --ai, joins the versus match of a host, like 127.0.0.1:7777
+  --max-ticks <N>     Stops the game after this many ticks, one hour by default
+  -h, --help          Print this message";
+
+/// Who plays the game
+enum Driver {
+    Script(PathBuf),
+    Ai { seed: u64, mode: GameMode },
+    /// AI playing a network versus match, the host picks the seed
+    Net { role: NetRole, seed: u64 }
+}
+
+struct Options {
+    driver: Driver,
+    max_ticks: u64
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => return ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match options.driver {
+        Driver::Script(path) => {
+            let replay = match Replay::load(&path) {
+                Ok(replay) => replay,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            let start = Instant::now();
+            let mut player = ReplayPlayer::new(&replay);
+            while !player.game().is_gameover() && player.game().ticks() < options.max_ticks {
+                player.step();
+                player.drain_events();
+            }
+
+            print_stats(player.game(), start);
+        },
+        Driver::Ai { seed, mode } => {
+            let start = Instant::now();
+            let mut game = TetrisGame::with_settings(seed, GameSettings { mode, ..GameSettings::default() });
+            let mut ai = Ai::default();
+            while !game.is_gameover() && game.ticks() < options.max_ticks {
+                // Same pacing as the AI in the game window, a seed gives the same game in both
+                if let Some(input) = ai.paced_input(&game) {
+                    game.handle_input(input);
+                }
+
+                game.tick();
+                game.drain_events();
+            }
+
+            print_stats(&game, start);
+        },
+        Driver::Net { role, seed } => {
+            if let Err(e) = play_net(role, seed, options.max_ticks) {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Lets the AI play the local player of a network match, then prints the result and the final state hash to compare both sides
+fn play_net(role: NetRole, seed: u64, max_ticks: u64) -> Result<(), String> {
+    let mut session = match role {
+        NetRole::Host(port) => {
+            let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|e| format!("can't listen on port {}: {}", port, e))?;
+            println!("waiting for a player on port {}", port);
+            NetSession::host(&listener, seed, GameSettings::default())?.ok_or("no player connected")?
+        },
+        NetRole::Join(address) => NetSession::join(&address)?
+    };
+
+    let start = Instant::now();
+    let player = session.local_player();
+    let mut ai = Ai::default();
+    while session.versus().result().is_none() && session.versus().game(player).ticks() < max_ticks {
+        if !session.update()? {
+            std::thread::yield_now();
+            continue;
+        }
+
+        // Inputs are played INPUT_DELAY ticks later, the AI waits to see where they got the tetris
+        let game = session.versus().game(player);
+        if game.ticks().is_multiple_of(INPUT_DELAY + 1) && let Some(input) = ai.next_input(game) {
+            session.give_input(input);
+        }
+        for player in 0..PLAYERS {
+            session.drain_events(player);
+        }
+    }
+    session.leave();
+
+    let versus = session.versus();
+    println!("player: {}", player + 1);
+    println!("seed: {}", versus.seed());
+    println!("result: {}", match versus.result() {
+        Some(VersusResult::Winner(winner)) => format!("player {} wins", winner + 1),
+        Some(VersusResult::Draw) => "draw".to_string(),
+        None => "tick limit".to_string()
+    });
+    for index in 0..PLAYERS {
+        println!("player {}: {} lines, {} garbage sent", index + 1, versus.game(index).lines(), versus.garbage_sent(index));
+    }
+    println!("ticks: {}", (0..PLAYERS).map(|index| versus.game(index).ticks()).max().unwrap_or_default());
+    println!("state hash: {:016x}", state_hash(versus));
+    println!("played in: {:.3}s", start.elapsed().as_secs_f64());
+    Ok(())
+}
+
+fn print_stats(game: &TetrisGame, start: Instant) {
+    let elapsed = start.elapsed();
+    let GameStats { seed, score, lines, level, pieces, ticks, goal_reached } = game.stats();
+
+    println!("seed: {}", seed);
+    println!("mode: {:?}", game.settings().mode);
+    println!("score: {}", score);
+    println!("lines: {}", lines);
+    println!("level: {}", level);
+    println!("pieces: {}", pieces);
+    println!("ticks: {} ({:.2}s of play)", ticks, ticks as f64 / TICKS_PER_SECOND as f64);
+    println!("ended: {}", if goal_reached {
+        "goal reached"
+    } else if game.is_gameover() {
+        "topped out"
+    } else {
+        "tick limit"
+    });
+    println!("simulated in: {:.3}s ({:.0} ticks/s)", elapsed.as_secs_f64(), ticks as f64 / elapsed.as_secs_f64().max(f64::EPSILON));
+}
+
+/// Ok(None) means the usage was printed and there is nothing to run
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut script = None;
+    let mut ai = false;
+    let mut seed = None;
+    let mut mode = GameMode::default();
+    let mut max_ticks = DEFAULT_MAX_TICKS;
+    let mut net_role = None;
+
+    while let Some(arg) = args.next() {
+        // Accept both `--seed 12` and `--seed=12`
+        let (name, inline_value) = split_option(arg);
+
+        if name == "-h" || name == "--help" {
+            println!("{}", USAGE);
+            return Ok(None);
+        }
+        if name == "--ai" {
+            ai = true;
+            continue;
+        }
+
+        let value = inline_value.or_else(|| args.next()).ok_or(format!("missing value for {}\n\n{}", name, USAGE))?;
+        match name.as_str() {
+            "--script" => script = Some(PathBuf::from(value)),
+            "--seed" => seed = Some(parse_value(&name, &value)?),
+            "--mode" => mode = parse_value(&name, &value)?,
+            "--max-ticks" => max_ticks = parse_value(&name, &value)?,
+            "--host" => net_role = Some(NetRole::Host(parse_value(&name, &value)?)),
+            "--join" => net_role = Some(NetRole::Join(value)),
+            _ => return Err(format!("unknown option {}\n\n{}", name, USAGE))
+        }
+    }
+
+    let driver = match (script, ai) {
+        (Some(script), false) if net_role.is_none() => Driver::Script(script),
+        (None, true) => match net_role {
+            Some(role) => Driver::Net { role, seed: seed.unwrap_or_else(rand::random) },
+            None => Driver::Ai { seed: seed.unwrap_or_else(rand::random), mode }
+        },
+        _ => return Err(format!("either --script or --ai is needed\n\n{}", USAGE))
+    };
+    Ok(Some(Options { driver, max_ticks }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Options of a command line given as one string, without `--help`
+    fn parse_words(words: &str) -> Result<Options, String> {
+        parse_args(words.split_whitespace().map(String::from)).map(|options| options.unwrap())
+    }
+
+    #[test]
+    fn options_are_read_in_both_forms() {
+        let options = parse_words("--ai --seed=7 --mode sprint --max-ticks=600").unwrap();
+        assert_eq!(options.max_ticks, 600);
+        assert!(matches!(options.driver, Driver::Ai { seed: 7, mode: GameMode::Sprint }));
+
+        let options = parse_words("--script=last.replay").unwrap();
+        assert!(matches!(options.driver, Driver::Script(path) if path.as_os_str() == "last.replay"));
+        assert_eq!(options.max_ticks, DEFAULT_MAX_TICKS);
+
+        let options = parse_words("--ai --join=127.0.0.1:7777").unwrap();
+        assert!(matches!(options.driver, Driver::Net { role: NetRole::Join(address), .. } if address == "127.0.0.1:7777"));
+        assert!(matches!(parse_words("--ai --host 7777").unwrap().driver, Driver::Net { role: NetRole::Host(7777), .. }));
+    }
+
+    #[test]
+    fn bad_command_lines_are_refused() {
+        let bad = [
+            "", "--ai --script last.replay", "--script last.replay --host 7777", "--ai --seed", "--ai --seed=seven",
+            "--ai --mode zen", "--ai --host 70000", "--ai --speed 2"
+        ];
+
+        for words in bad {
+            assert!(parse_words(words).is_err(), "accepted {}", words);
+        }
+    }
+
+    #[test]
+    fn help_stops_the_program() {
+        assert!(parse_args(["--help".to_string()].into_iter()).unwrap().is_none());
+    }
+}
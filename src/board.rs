@@ -0,0 +1,209 @@
+use std::ops::{Index, IndexMut};
+
+use crate::tetris::{Tetris, TetrisType};
+
+/// Thinnest board a line still fits in flat
+pub const MIN_BOARD_WIDTH: usize = 4;
+/// Shortest board a tetris still fits in standing up
+pub const MIN_BOARD_HEIGHT: usize = 4;
+/// Widest board, four times the usual one
+pub const MAX_BOARD_WIDTH: usize = 40;
+/// Tallest visible field, twice the usual one
+pub const MAX_BOARD_HEIGHT: usize = 40;
+/// Rows hidden above the visible field, tetris spawn at the bottom of them
+pub const BUFFER_HEIGHT: usize = 20;
+
+/// Game grid, sized at game creation
+///
+/// Rows are indexed from the top, so `board[y][x]` gives the square at column x of row y.
+/// The first [`BUFFER_HEIGHT`] rows are the hidden buffer, the visible field comes after them
+#[derive(Clone, Debug, PartialEq)]
+pub struct Board {
+    width: usize,
+    /// Rows of the buffer and the visible field together
+    height: usize,
+    /// Squares stored row after row
+    squares: Vec<Option<TetrisType>>
+}
+
+impl Board {
+    /// Makes an empty board with `height` visible rows under the buffer.
+    /// Sizes are clamped between [`MIN_BOARD_WIDTH`] and [`MAX_BOARD_WIDTH`], [`MIN_BOARD_HEIGHT`] and [`MAX_BOARD_HEIGHT`]
+    pub fn new(width: usize, height: usize) -> Self {
+        let width = width.clamp(MIN_BOARD_WIDTH, MAX_BOARD_WIDTH);
+        let height = height.clamp(MIN_BOARD_HEIGHT, MAX_BOARD_HEIGHT) + BUFFER_HEIGHT;
+
+        Self {
+            width,
+            height,
+            squares: vec![None; width * height]
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows, buffer included
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of rows of the visible field
+    pub fn visible_height(&self) -> usize {
+        self.height - BUFFER_HEIGHT
+    }
+
+    /// True if the row is in the hidden buffer, or above it
+    pub fn is_hidden(&self, y: i32) -> bool {
+        y < BUFFER_HEIGHT as i32
+    }
+
+    /// Iterates over the rows of the visible field, from top to bottom
+    pub fn visible_rows(&self) -> std::iter::Skip<std::slice::Chunks<'_, Option<TetrisType>>> {
+        self.iter().skip(BUFFER_HEIGHT)
+    }
+
+    /// Iterates over the rows, from top to bottom
+    pub fn iter(&self) -> std::slice::Chunks<'_, Option<TetrisType>> {
+        self.squares.chunks(self.width)
+    }
+
+    /// True if the square is outside the walls or floor, or already taken.
+    /// Rows above the board are open
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        x < 0 || x >= self.width as i32 || y >= self.height as i32 ||
+            (y >= 0 && self[y as usize][x as usize].is_some())
+    }
+
+    /// True if no square of the tetris is blocked
+    pub fn fits(&self, tetris: &Tetris) -> bool {
+        tetris.map.iter().enumerate().all(|(yindex, row)| {
+            row.iter().enumerate().all(|(xindex, filled)| {
+                !filled || !self.is_blocked(xindex as i32 + tetris.position.x, yindex as i32 + tetris.position.y)
+            })
+        })
+    }
+
+    /// True if every square of the row is taken
+    pub fn is_line_full(&self, y: usize) -> bool {
+        self[y].iter().all(|square| square.is_some())
+    }
+
+    /// Empty squares with a filled square somewhere above them in their column
+    pub fn holes(&self) -> usize {
+        (0..self.width)
+            .map(|x| {
+                let column = || (0..self.height).map(move |y| self[y][x].is_some());
+                column().skip_while(|filled| !filled).filter(|filled| !filled).count()
+            })
+            .sum()
+    }
+
+    /// Pushes every row up and fills the bottom `lines` rows with garbage, leaving the `hole` column empty
+    ///
+    /// Returns true if taken squares were pushed off the top
+    pub fn add_garbage(&mut self, lines: usize, hole: usize) -> bool {
+        let lines = lines.min(self.height);
+        let overflow = self.squares[..lines * self.width].iter().any(Option::is_some);
+
+        self.squares.copy_within(lines * self.width.., 0);
+        let garbage_start = (self.height - lines) * self.width;
+        for (index, square) in self.squares[garbage_start..].iter_mut().enumerate() {
+            *square = (index % self.width != hole).then_some(TetrisType::Garbage);
+        }
+
+        overflow
+    }
+
+    /// Deletes the row and shifts all rows above it down, leaving an empty row at the top
+    pub fn clear_line(&mut self, y: usize) {
+        // Shift rows down, starting from the bottom so nothing gets overwritten
+        self.squares.copy_within(0..y * self.width, self.width);
+        self.squares[..self.width].fill(None);
+    }
+}
+
+impl Index<usize> for Board {
+    type Output = [Option<TetrisType>];
+
+    fn index(&self, y: usize) -> &Self::Output {
+        &self.squares[y * self.width..(y + 1) * self.width]
+    }
+}
+
+impl IndexMut<usize> for Board {
+    fn index_mut(&mut self, y: usize) -> &mut Self::Output {
+        &mut self.squares[y * self.width..(y + 1) * self.width]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_are_raised_to_the_minimum() {
+        let board = Board::new(1, 2);
+        assert_eq!(board.width(), MIN_BOARD_WIDTH);
+        assert_eq!(board.visible_height(), MIN_BOARD_HEIGHT);
+        assert_eq!(board.height(), MIN_BOARD_HEIGHT + BUFFER_HEIGHT);
+        assert_eq!(board.visible_rows().count(), MIN_BOARD_HEIGHT);
+    }
+
+    #[test]
+    fn sizes_are_lowered_to_the_maximum() {
+        let board = Board::new(usize::MAX, 1_000_000_000);
+        assert_eq!(board.width(), MAX_BOARD_WIDTH);
+        assert_eq!(board.visible_height(), MAX_BOARD_HEIGHT);
+    }
+
+    #[test]
+    fn walls_and_floor_are_blocked() {
+        let mut board = Board::new(6, 8);
+        board[BUFFER_HEIGHT + 2][3] = Some(TetrisType::Square);
+
+        assert!(board.is_blocked(-1, 0));
+        assert!(board.is_blocked(6, 0));
+        assert!(board.is_blocked(0, board.height() as i32));
+        assert!(board.is_blocked(3, BUFFER_HEIGHT as i32 + 2));
+        assert!(!board.is_blocked(2, BUFFER_HEIGHT as i32 + 2));
+        assert!(!board.is_blocked(0, -3));
+    }
+
+    #[test]
+    fn clearing_a_line_shifts_the_rows_above() {
+        let mut board = Board::new(4, 4);
+        let bottom = board.height() - 1;
+        board[bottom].fill(Some(TetrisType::Line));
+        board[bottom - 1][1] = Some(TetrisType::TShape);
+        assert!(board.is_line_full(bottom));
+
+        board.clear_line(bottom);
+
+        assert!(!board.is_line_full(bottom));
+        assert_eq!(board[bottom], [None, Some(TetrisType::TShape), None, None]);
+        assert!(board[bottom - 1].iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn garbage_pushes_the_stack_up() {
+        let mut board = Board::new(4, 4);
+        let bottom = board.height() - 1;
+        board[bottom][0] = Some(TetrisType::Square);
+
+        assert!(!board.add_garbage(2, 3));
+
+        assert_eq!(board[bottom - 2][0], Some(TetrisType::Square));
+        for y in [bottom - 1, bottom] {
+            assert_eq!(board[y], [Some(TetrisType::Garbage), Some(TetrisType::Garbage), Some(TetrisType::Garbage), None]);
+        }
+        assert_eq!(board.holes(), 0);
+        board[bottom - 2][3] = Some(TetrisType::Square);
+        assert_eq!(board.holes(), 2);
+
+        // Pushing squares off the top reports the overflow
+        board[0][0] = Some(TetrisType::Square);
+        assert!(board.add_garbage(1, 0));
+    }
+}
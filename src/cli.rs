@@ -0,0 +1,167 @@
+use std::path::PathBuf;
+
+use tetris_sdl_rs::args::{parse_value, split_option};
+use tetris_sdl_rs::{GameSettings, NetRole};
+
+/// Pixels per square when `--scale` isn't given
+pub const DEFAULT_SCALE: usize = 50;
+/// Biggest `--scale`, the largest boards side by side still fit the sizes SDL takes
+const MAX_SCALE: usize = 200;
+/// File the last game is recorded to when `--record` isn't given
+pub const DEFAULT_RECORD_PATH: &str = "last.replay";
+
+const USAGE: &str = "Usage: tetris-sdl-rs [OPTIONS]
+
+Options:
+  --seed <N>          Seed of every game, random for each game by default
+  --level <N>         Start level (1-20)
+  --width <N>         Board width in squares (4-40)
+  --height <N>        Visible board height in squares (4-40)
+  --randomizer <R>    bag7, bag14, random or nes
+  --rotation <R>      srs, nokick or ars
+  --scale <N>         Pixels per square of the window (1-200)
+  --record <FILE>     Where each finished game is recorded, last.replay by default
+  --replay <FILE>     Plays a recorded game back instead of playing
+  --ai                Lets the built-in AI play the games
+  --host <PORT>       Waits for a player on the port and plays a versus match over the network
+  --join <ADDR>       Joins the versus match of a host, like 192.168.1.20:7777
+  --spectate <ADDR>   Streams the games as JSON lines to viewers connecting to the address, like 127.0.0.1:9000
+  -h, --help          Print this message";
+
+/// Options given on the command line
+pub struct Options {
+    /// Fixed seed, None picks a new one for each game
+    pub seed: Option<u64>,
+    /// Starting settings, still changeable in the settings screen
+    pub settings: GameSettings,
+    /// Pixels per square
+    pub scale: usize,
+    /// File finished games are recorded to
+    pub record: PathBuf,
+    /// Replay to watch instead of playing
+    pub replay: Option<PathBuf>,
+    /// True if the AI plays instead of the player
+    pub ai: bool,
+    /// Network versus match to play instead of the menus
+    pub net: Option<NetRole>,
+    /// Address spectators connect to, None doesn't open any socket
+    pub spectate: Option<String>
+}
+
+impl Options {
+    /// Seed to use for the next game
+    pub fn next_seed(&self) -> u64 {
+        self.seed.unwrap_or_else(rand::random)
+    }
+}
+
+/// Reads the options from the process arguments
+///
+/// Returns Ok(None) when the help was printed and the program should exit
+pub fn parse() -> Result<Option<Options>, String> {
+    parse_args(std::env::args().skip(1))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options {
+        seed: None,
+        settings: GameSettings::default(),
+        scale: DEFAULT_SCALE,
+        record: PathBuf::from(DEFAULT_RECORD_PATH),
+        replay: None,
+        ai: false,
+        net: None,
+        spectate: None
+    };
+
+    while let Some(arg) = args.next() {
+        // Accept both `--seed 12` and `--seed=12`
+        let (name, inline_value) = split_option(arg);
+
+        if name == "-h" || name == "--help" {
+            println!("{}", USAGE);
+            return Ok(None);
+        }
+        if name == "--ai" {
+            options.ai = true;
+            continue;
+        }
+
+        let value = match inline_value.or_else(|| args.next()) {
+            Some(value) => value,
+            None => return Err(format!("missing value for {}\n\n{}", name, USAGE))
+        };
+
+        match name.as_str() {
+            "--seed" => options.seed = Some(parse_value(&name, &value)?),
+            "--level" => options.settings.start_level = parse_value(&name, &value)?,
+            "--width" => options.settings.width = parse_value(&name, &value)?,
+            "--height" => options.settings.height = parse_value(&name, &value)?,
+            "--randomizer" => options.settings.randomizer = parse_value(&name, &value)?,
+            "--rotation" => options.settings.rotation_system = parse_value(&name, &value)?,
+            "--scale" => {
+                options.scale = parse_value(&name, &value)?;
+                if !(1..=MAX_SCALE).contains(&options.scale) {
+                    return Err(format!("scale must be between 1 and {}, got {}", MAX_SCALE, options.scale));
+                }
+            },
+            "--record" => options.record = PathBuf::from(value),
+            "--replay" => options.replay = Some(PathBuf::from(value)),
+            "--host" => options.net = Some(NetRole::Host(parse_value(&name, &value)?)),
+            "--join" => options.net = Some(NetRole::Join(value)),
+            "--spectate" => options.spectate = Some(value),
+            _ => return Err(format!("unknown option {}\n\n{}", name, USAGE))
+        }
+    }
+
+    options.settings.check()?;
+    if options.net.is_some() && (options.ai || options.replay.is_some()) {
+        return Err("--host and --join can't be used with --ai or --replay".to_string());
+    }
+
+    Ok(Some(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use tetris_sdl_rs::{Randomizer, RotationSystemKind};
+
+    use super::*;
+
+    /// Parses the words like a command line, the help is never asked
+    fn parse_words(words: &str) -> Result<Options, String> {
+        parse_args(words.split_whitespace().map(String::from)).map(|options| options.unwrap())
+    }
+
+    #[test]
+    fn options_are_read_in_both_forms() {
+        let options = parse_words("--seed 42 --level=5 --width 12 --height=24 --randomizer nes --rotation ars --ai").unwrap();
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.next_seed(), 42);
+        assert_eq!(options.settings.start_level, 5);
+        assert_eq!((options.settings.width, options.settings.height), (12, 24));
+        assert_eq!(options.settings.randomizer, Randomizer::Nes);
+        assert_eq!(options.settings.rotation_system, RotationSystemKind::Ars);
+        assert!(options.ai);
+        assert_eq!(options.scale, DEFAULT_SCALE);
+    }
+
+    #[test]
+    fn out_of_range_values_are_refused() {
+        let bad = [
+            "--level 0", "--level 21", "--width 3", "--width 41", "--height 100", "--scale 0", "--scale 201",
+            "--seed twelve", "--randomizer bag8", "--colors", "--seed", "--host 7777 --ai", "--join 127.0.0.1:7777 --replay last.replay"
+        ];
+
+        for words in bad {
+            assert!(parse_words(words).is_err(), "accepted {}", words);
+        }
+        // Settings out of range give the same message as a bad replay
+        assert_eq!(parse_words("--width 41").err(), Some("width must be between 4 and 40, got 41".to_string()));
+    }
+
+    #[test]
+    fn help_stops_the_program() {
+        assert!(parse_args(["--help".to_string()].into_iter()).unwrap().is_none());
+    }
+}
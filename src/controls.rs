@@ -0,0 +1,545 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use sdl2::controller::{Axis, Button};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use serde::{Deserialize, Serialize};
+use tetris_sdl_rs::{Input, PLAYERS};
+
+use crate::dirs::config_dir;
+
+const FILE_NAME: &str = "controls.toml";
+/// Part of the axis range ignored around the center, as a fraction
+const DEFAULT_DEAD_ZONE: f32 = 0.25;
+/// Dead zone change of one step on the controls screen
+const DEAD_ZONE_STEP: f32 = 0.05;
+
+/// What the player can do in game, each one can have several keys and buttons
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    RotateCcw,
+    RotateCw,
+    SoftDrop,
+    HardDrop,
+    Hold,
+    Pause
+}
+
+impl Action {
+    /// Every action, in the order of the controls screen
+    pub const ALL: [Action; 8] = [
+        Action::MoveLeft, Action::MoveRight, Action::RotateCcw, Action::RotateCw,
+        Action::SoftDrop, Action::HardDrop, Action::Hold, Action::Pause
+    ];
+
+    /// Key of the action in the config file
+    fn config_name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::RotateCcw => "rotate_ccw",
+            Action::RotateCw => "rotate_cw",
+            Action::SoftDrop => "soft_drop",
+            Action::HardDrop => "hard_drop",
+            Action::Hold => "hold",
+            Action::Pause => "pause"
+        }
+    }
+
+    /// Name shown on the controls screen
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::RotateCcw => "Rotate left",
+            Action::RotateCw => "Rotate right",
+            Action::SoftDrop => "Soft drop",
+            Action::HardDrop => "Hard drop",
+            Action::Hold => "Hold",
+            Action::Pause => "Pause"
+        }
+    }
+
+    /// Game input sent when the action is pressed (true) or released, None for pause and for releases that don't matter
+    pub fn input(self, pressed: bool) -> Option<Input> {
+        match (self, pressed) {
+            (Action::MoveLeft, true) => Some(Input::MoveLeftStart),
+            (Action::MoveLeft, false) => Some(Input::MoveLeftEnd),
+            (Action::MoveRight, true) => Some(Input::MoveRightStart),
+            (Action::MoveRight, false) => Some(Input::MoveRightEnd),
+            (Action::SoftDrop, true) => Some(Input::SoftDropStart),
+            (Action::SoftDrop, false) => Some(Input::SoftDropEnd),
+            (Action::RotateCcw, true) => Some(Input::RotateLeft),
+            (Action::RotateCw, true) => Some(Input::RotateRight),
+            (Action::HardDrop, true) => Some(Input::HardDrop),
+            (Action::Hold, true) => Some(Input::Hold),
+            _ => None
+        }
+    }
+
+    fn index(self) -> usize {
+        Action::ALL.iter().position(|action| *action == self).unwrap_or_default()
+    }
+}
+
+/// Side an analog stick or trigger is pushed to, triggers only go positive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AxisDirection {
+    Negative,
+    Positive
+}
+
+impl AxisDirection {
+    fn sign(self) -> &'static str {
+        match self {
+            AxisDirection::Negative => "-",
+            AxisDirection::Positive => "+"
+        }
+    }
+}
+
+/// A keyboard key, a controller button or an analog axis pushed to one side
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(Keycode),
+    Button(Button),
+    Axis(Axis, AxisDirection)
+}
+
+impl Binding {
+    /// Binding pressed by the event, key repeats are ignored.
+    /// Axes count once pushed half way, the game uses [`Controls::changes`] to follow them with the dead zone
+    pub fn pressed(event: &Event) -> Option<Binding> {
+        match event {
+            Event::KeyDown { keycode: Some(keycode), repeat: false, .. } => Some(Binding::Key(*keycode)),
+            Event::ControllerButtonDown { button, .. } => Some(Binding::Button(*button)),
+            Event::ControllerAxisMotion { axis, value, .. } => axis_direction(*value, 0.5).map(|direction| Binding::Axis(*axis, direction)),
+            _ => None
+        }
+    }
+
+    /// Binding released by the event
+    pub fn released(event: &Event) -> Option<Binding> {
+        match event {
+            Event::KeyUp { keycode: Some(keycode), .. } => Some(Binding::Key(*keycode)),
+            Event::ControllerButtonUp { button, .. } => Some(Binding::Button(*button)),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(keycode) => keycode.name(),
+            Binding::Button(button) => format!("Pad {}", button.string()),
+            Binding::Axis(axis, direction) => format!("Pad {}{}", axis.string(), direction.sign())
+        }
+    }
+
+    /// Name in the controller section of the config file, None for keys
+    fn config_value(&self) -> Option<String> {
+        match self {
+            Binding::Key(_) => None,
+            Binding::Button(button) => Some(button.string()),
+            Binding::Axis(axis, direction) => Some(format!("{}{}", axis.string(), direction.sign()))
+        }
+    }
+
+    /// Reads a button name like "a", or an axis name followed by its side like "leftx-"
+    fn from_config_value(value: &str) -> Option<Binding> {
+        if let Some(axis) = value.strip_suffix('+') {
+            return Axis::from_string(axis).map(|axis| Binding::Axis(axis, AxisDirection::Positive));
+        }
+        if let Some(axis) = value.strip_suffix('-') {
+            return Axis::from_string(axis).map(|axis| Binding::Axis(axis, AxisDirection::Negative));
+        }
+
+        Button::from_string(value).map(Binding::Button)
+    }
+}
+
+/// Side the axis value is pushed to, None while it is inside the dead zone
+fn axis_direction(value: i16, dead_zone: f32) -> Option<AxisDirection> {
+    let threshold = dead_zone * i16::MAX as f32;
+    if value as f32 > threshold {
+        Some(AxisDirection::Positive)
+    } else if (value as f32) < -threshold {
+        Some(AxisDirection::Negative)
+    } else {
+        None
+    }
+}
+
+/// Layout of the config file, one list of key or button names per action
+///
+/// ```toml
+/// dead_zone = 0.25
+///
+/// [keyboard]
+/// move_left = ["Left", "A"]
+///
+/// [controller]
+/// move_left = ["dpleft", "leftx-"]
+/// ```
+#[derive(Default, Serialize, Deserialize)]
+struct ControlsFile {
+    dead_zone: Option<f32>,
+    #[serde(default)]
+    keyboard: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    controller: BTreeMap<String, Vec<String>>
+}
+
+/// Keys and buttons of every action, saved in the user config directory
+pub struct Controls {
+    /// Bindings indexed like [`Action::ALL`]
+    bindings: Vec<Vec<Binding>>,
+    /// Part of the axis range around the center that is ignored, between 0 and 1
+    dead_zone: f32,
+    /// Side each axis of each controller is pushed to, to only react when it changes
+    axes: HashMap<(u32, Axis), AxisDirection>,
+    /// None when no config directory could be found, changes then only last until the game closes
+    path: Option<PathBuf>,
+    /// Set when the file couldn't be read or written, shown on the controls screen
+    pub error: Option<String>
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        let defaults = |action: Action| match action {
+            Action::MoveLeft => vec![
+                Binding::Key(Keycode::Left), Binding::Button(Button::DPadLeft), Binding::Axis(Axis::LeftX, AxisDirection::Negative)
+            ],
+            Action::MoveRight => vec![
+                Binding::Key(Keycode::Right), Binding::Button(Button::DPadRight), Binding::Axis(Axis::LeftX, AxisDirection::Positive)
+            ],
+            Action::RotateCcw => vec![Binding::Key(Keycode::X), Binding::Button(Button::A)],
+            Action::RotateCw => vec![Binding::Key(Keycode::C), Binding::Button(Button::B)],
+            Action::SoftDrop => vec![
+                Binding::Key(Keycode::Down), Binding::Button(Button::DPadDown), Binding::Axis(Axis::LeftY, AxisDirection::Positive)
+            ],
+            Action::HardDrop => vec![Binding::Key(Keycode::Space), Binding::Button(Button::DPadUp)],
+            Action::Hold => vec![
+                Binding::Key(Keycode::LShift), Binding::Button(Button::Y), Binding::Axis(Axis::TriggerLeft, AxisDirection::Positive)
+            ],
+            Action::Pause => vec![Binding::Key(Keycode::Escape), Binding::Key(Keycode::P), Binding::Button(Button::Start)]
+        };
+
+        Self {
+            bindings: Action::ALL.iter().map(|action| defaults(*action)).collect(),
+            dead_zone: DEFAULT_DEAD_ZONE,
+            axes: HashMap::new(),
+            path: config_dir().map(|dir| dir.join(FILE_NAME)),
+            error: None
+        }
+    }
+}
+
+impl Controls {
+    /// Reads the bindings from `$XDG_CONFIG_HOME/tetris-sdl-rs/controls.toml`, or `~/.config/tetris-sdl-rs/controls.toml`
+    ///
+    /// Actions missing from the file keep their default bindings. A broken file is reported in `error`,
+    /// renamed so it isn't overwritten and every action gets its defaults
+    pub fn load() -> Self {
+        let mut controls = Controls::default();
+        let Some(path) = controls.path.clone() else {
+            return controls;
+        };
+
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return controls,
+            Err(e) => {
+                // Saving would replace a file we never read
+                controls.path = None;
+                controls.error = Some(format!("can't read {}: {}, changes won't be saved", path.display(), e));
+                eprintln!("{}", e);
+                return controls;
+            }
+        };
+
+        if let Err(e) = controls.apply_file(&text) {
+            let backup = path.with_extension("toml.corrupt");
+            controls.error = Some(match std::fs::rename(&path, &backup) {
+                Ok(()) => format!("controls were broken ({}), moved to {}", e, backup.display()),
+                Err(_) => {
+                    controls.path = None;
+                    format!("{}: {}, changes won't be saved", path.display(), e)
+                }
+            });
+        }
+        if let Some(error) = &controls.error {
+            eprintln!("{}", error);
+        }
+        controls
+    }
+
+    /// Replaces the bindings of every action found in the file. Nothing changes if any part of it is wrong
+    fn apply_file(&mut self, text: &str) -> Result<(), String> {
+        let file: ControlsFile = toml::from_str(text).map_err(|e| e.to_string())?;
+
+        let dead_zone = file.dead_zone.unwrap_or(self.dead_zone);
+        if !(0.0..1.0).contains(&dead_zone) {
+            return Err(format!("dead_zone must be between 0 and 1, found {}", dead_zone));
+        }
+
+        // Actions in the file lose their defaults, even when they only have bindings in one section
+        let mut bindings = self.bindings.clone();
+        for action in Action::ALL {
+            if file.keyboard.contains_key(action.config_name()) || file.controller.contains_key(action.config_name()) {
+                bindings[action.index()].clear();
+            }
+        }
+
+        for (section, is_keyboard) in [(&file.keyboard, true), (&file.controller, false)] {
+            for (name, values) in section {
+                let action = Action::ALL
+                    .into_iter()
+                    .find(|action| action.config_name() == name)
+                    .ok_or(format!("unknown action \"{}\"", name))?;
+
+                for value in values {
+                    let binding = if is_keyboard {
+                        Keycode::from_name(value).map(Binding::Key)
+                    } else {
+                        Binding::from_config_value(value)
+                    };
+                    let binding = binding.ok_or(format!("unknown key or button \"{}\" for {}", value, name))?;
+                    bindings[action.index()].push(binding);
+                }
+            }
+        }
+
+        self.bindings = bindings;
+        self.dead_zone = dead_zone;
+        Ok(())
+    }
+
+    /// Writes every binding to the config file
+    pub fn save(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let mut file = ControlsFile {
+            dead_zone: Some(self.dead_zone),
+            ..ControlsFile::default()
+        };
+        for action in Action::ALL {
+            let bindings = &self.bindings[action.index()];
+            let keys = bindings.iter().filter_map(|binding| match binding {
+                Binding::Key(keycode) => Some(keycode.name()),
+                Binding::Button(_) | Binding::Axis(..) => None
+            });
+            let buttons = bindings.iter().filter_map(Binding::config_value);
+            file.keyboard.insert(action.config_name().to_string(), keys.collect());
+            file.controller.insert(action.config_name().to_string(), buttons.collect());
+        }
+
+        let result = toml::to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(path, text).map_err(|e| e.to_string())
+            });
+
+        self.error = result.err().map(|e| format!("can't write {}: {}", path.display(), e));
+    }
+
+    /// Actions pressed (true) or released (false) by the event
+    ///
+    /// An axis presses its binding when it leaves the dead zone and releases it when it comes back,
+    /// going from one side to the other straight away does both
+    pub fn changes(&mut self, event: &Event) -> Vec<(Action, bool)> {
+        let changed = if let Event::ControllerAxisMotion { which, axis, value, .. } = *event {
+            let direction = axis_direction(value, self.dead_zone);
+            let previous = match direction {
+                Some(direction) => self.axes.insert((which, axis), direction),
+                None => self.axes.remove(&(which, axis))
+            };
+            if previous == direction {
+                return Vec::new();
+            }
+
+            let released = previous.map(|previous| (Binding::Axis(axis, previous), false));
+            let pressed = direction.map(|direction| (Binding::Axis(axis, direction), true));
+            released.into_iter().chain(pressed).collect()
+        } else {
+            let pressed = Binding::pressed(event).map(|binding| (binding, true));
+            let released = Binding::released(event).map(|binding| (binding, false));
+            pressed.into_iter().chain(released).collect::<Vec<_>>()
+        };
+
+        changed
+            .into_iter()
+            .filter_map(|(binding, pressed)| self.action_of(binding).map(|action| (action, pressed)))
+            .collect()
+    }
+
+    /// Controls of a versus player: a fixed half of the keyboard, with the controller bindings and dead zone of these controls
+    ///
+    /// Player 1 uses WASD with Q, E and left Shift, player 2 the arrows with comma, period and right Shift.
+    /// Escape pauses for both. They are never saved
+    pub fn for_player(&self, player: usize) -> Controls {
+        let keys = |action: Action| match (action, player.min(PLAYERS - 1)) {
+            (Action::MoveLeft, 0) => vec![Keycode::A],
+            (Action::MoveRight, 0) => vec![Keycode::D],
+            (Action::SoftDrop, 0) => vec![Keycode::S],
+            (Action::HardDrop, 0) => vec![Keycode::W],
+            (Action::RotateCcw, 0) => vec![Keycode::Q],
+            (Action::RotateCw, 0) => vec![Keycode::E],
+            (Action::Hold, 0) => vec![Keycode::LShift],
+            (Action::MoveLeft, _) => vec![Keycode::Left],
+            (Action::MoveRight, _) => vec![Keycode::Right],
+            (Action::SoftDrop, _) => vec![Keycode::Down],
+            (Action::HardDrop, _) => vec![Keycode::Up],
+            (Action::RotateCcw, _) => vec![Keycode::Comma],
+            (Action::RotateCw, _) => vec![Keycode::Period],
+            (Action::Hold, _) => vec![Keycode::RShift],
+            (Action::Pause, _) => vec![Keycode::Escape]
+        };
+
+        let bindings = Action::ALL
+            .iter()
+            .map(|action| {
+                let pad = self.bindings[action.index()].iter().filter(|binding| !matches!(binding, Binding::Key(_)));
+                keys(*action).into_iter().map(Binding::Key).chain(pad.copied()).collect()
+            })
+            .collect();
+
+        Controls {
+            bindings,
+            dead_zone: self.dead_zone,
+            axes: HashMap::new(),
+            path: None,
+            error: None
+        }
+    }
+
+    /// Forgets the axes of an unplugged controller, its instance id isn't reused
+    pub fn forget_pad(&mut self, which: u32) {
+        self.axes.retain(|(pad, _), _| *pad != which);
+    }
+
+    pub fn dead_zone(&self) -> f32 {
+        self.dead_zone
+    }
+
+    /// Changes the dead zone by steps of 5%, staying between 5% and 95%
+    pub fn adjust_dead_zone(&mut self, delta: i32) {
+        let dead_zone = self.dead_zone + delta as f32 * DEAD_ZONE_STEP;
+        self.dead_zone = (dead_zone / DEAD_ZONE_STEP).round().clamp(1.0, 19.0) * DEAD_ZONE_STEP;
+    }
+
+    fn action_of(&self, binding: Binding) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|action| self.bindings[action.index()].contains(&binding))
+    }
+
+    /// Adds a binding to the action, taking it away from any other action
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        for bindings in self.bindings.iter_mut() {
+            bindings.retain(|bound| *bound != binding);
+        }
+        self.bindings[action.index()].push(binding);
+    }
+
+    /// Removes every binding of the action
+    pub fn clear(&mut self, action: Action) {
+        self.bindings[action.index()].clear();
+    }
+
+    /// Goes back to the default bindings and dead zone, the file is kept
+    pub fn reset(&mut self) {
+        let defaults = Controls::default();
+        self.bindings = defaults.bindings;
+        self.dead_zone = defaults.dead_zone;
+    }
+
+    /// Names of the bindings of the action, separated by commas
+    pub fn describe(&self, action: Action) -> String {
+        let bindings = &self.bindings[action.index()];
+        if bindings.is_empty() {
+            return "none".to_string();
+        }
+
+        bindings.iter().map(Binding::name).collect::<Vec<_>>().join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Names of the bindings of every action, to compare two sets of controls
+    fn described(controls: &Controls) -> Vec<String> {
+        Action::ALL.iter().map(|action| controls.describe(*action)).collect()
+    }
+
+    fn axis_motion(axis: Axis, value: i16) -> Event {
+        Event::ControllerAxisMotion { timestamp: 0, which: 0, axis, value }
+    }
+
+    #[test]
+    fn saved_controls_load_back() {
+        let dir = std::env::temp_dir().join(format!("tetris-controls-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut controls = Controls { path: Some(dir.join(FILE_NAME)), ..Controls::default() };
+        controls.bind(Action::Hold, Binding::Key(Keycode::Z));
+        controls.bind(Action::HardDrop, Binding::Axis(Axis::TriggerRight, AxisDirection::Positive));
+        controls.clear(Action::Pause);
+        controls.adjust_dead_zone(2);
+        controls.save();
+        assert_eq!(controls.error, None);
+
+        let mut loaded = Controls::default();
+        loaded.apply_file(&std::fs::read_to_string(dir.join(FILE_NAME)).unwrap()).unwrap();
+        assert_eq!(described(&loaded), described(&controls));
+        assert_eq!(loaded.dead_zone(), controls.dead_zone());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_only_replaces_the_actions_it_lists() {
+        let mut controls = Controls::default();
+        controls.apply_file("[controller]\nhold = [\"x\", \"lefty-\"]\n").unwrap();
+
+        assert_eq!(controls.action_of(Binding::Button(Button::X)), Some(Action::Hold));
+        assert_eq!(controls.action_of(Binding::Key(Keycode::LShift)), None);
+        assert_eq!(controls.action_of(Binding::Key(Keycode::Left)), Some(Action::MoveLeft));
+    }
+
+    #[test]
+    fn broken_file_changes_nothing() {
+        let broken = [
+            "[keyboard]\nmove_left = [\"J\"]\nhold = [\"NoSuchKey\"]\n",
+            "[keyboard]\nmove_left = [\"J\"]\nteleport = [\"T\"]\n",
+            "dead_zone = 1.5\n[keyboard]\nmove_left = [\"J\"]\n",
+            "[keyboard\nmove_left = [\"J\"]\n"
+        ];
+
+        for text in broken {
+            let mut controls = Controls::default();
+            assert!(controls.apply_file(text).is_err(), "accepted:\n{}", text);
+            assert_eq!(described(&controls), described(&Controls::default()));
+            assert_eq!(controls.dead_zone(), DEFAULT_DEAD_ZONE);
+        }
+    }
+
+    #[test]
+    fn axis_presses_once_out_of_the_dead_zone() {
+        let mut controls = Controls::default();
+
+        assert_eq!(controls.changes(&axis_motion(Axis::LeftX, -5000)), []);
+        assert_eq!(controls.changes(&axis_motion(Axis::LeftX, -20000)), [(Action::MoveLeft, true)]);
+        assert_eq!(controls.changes(&axis_motion(Axis::LeftX, -30000)), []);
+        assert_eq!(controls.changes(&axis_motion(Axis::LeftX, 30000)), [(Action::MoveLeft, false), (Action::MoveRight, true)]);
+        assert_eq!(controls.changes(&axis_motion(Axis::LeftX, 0)), [(Action::MoveRight, false)]);
+    }
+}
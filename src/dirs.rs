@@ -0,0 +1,24 @@
+//! Where the game keeps its files, following the XDG base directory spec
+
+use std::path::PathBuf;
+
+/// Settings like the controls
+pub fn config_dir() -> Option<PathBuf> {
+    app_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// Data like the high scores
+pub fn data_dir() -> Option<PathBuf> {
+    app_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// Directory of the game in the base directory given by `variable`, or by `fallback` in the home directory.
+/// Relative paths in the variable are ignored as the spec asks
+fn app_dir(variable: &str, fallback: &str) -> Option<PathBuf> {
+    let base = std::env::var_os(variable)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))?;
+
+    Some(base.join("tetris-sdl-rs"))
+}
@@ -0,0 +1,331 @@
+use std::collections::VecDeque;
+
+use crate::board::{Board, BUFFER_HEIGHT};
+use crate::game::{GameEvent, GameSettings, Input, TetrisGame};
+use crate::rotation::Rotation;
+use crate::tetris::{Position, TetrisMap, TetrisType};
+
+/// What an agent can do at each step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Lets the game run
+    Noop,
+    MoveLeft,
+    MoveRight,
+    RotateLeft,
+    RotateRight,
+    /// Fast falls for the length of the step
+    SoftDrop,
+    HardDrop,
+    Hold
+}
+
+impl Action {
+    /// Every action, agents with a discrete action space use the index as the action id
+    pub const ALL: [Action; 8] = [
+        Action::Noop, Action::MoveLeft, Action::MoveRight, Action::RotateLeft,
+        Action::RotateRight, Action::SoftDrop, Action::HardDrop, Action::Hold
+    ];
+
+    /// Input given to the game at the start of the step
+    fn input(self) -> Option<Input> {
+        match self {
+            Action::Noop => None,
+            Action::MoveLeft => Some(Input::MoveLeft),
+            Action::MoveRight => Some(Input::MoveRight),
+            Action::RotateLeft => Some(Input::RotateLeft),
+            Action::RotateRight => Some(Input::RotateRight),
+            Action::SoftDrop => Some(Input::SoftDropStart),
+            Action::HardDrop => Some(Input::HardDrop),
+            Action::Hold => Some(Input::Hold)
+        }
+    }
+}
+
+/// How rewards are computed from a step
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RewardShaping {
+    /// Reward for clearing 0, 1, 2, 3 and 4 lines at once
+    pub lines: [f64; 5],
+    /// Added for each hole made by the step, removed for each hole filled or cleared
+    pub hole: f64,
+    /// Added on every step the game goes on
+    pub survival: f64,
+    /// Added on the step the game tops out
+    pub game_over: f64
+}
+
+impl Default for RewardShaping {
+    fn default() -> Self {
+        Self {
+            lines: [0.0, 1.0, 3.0, 5.0, 8.0],
+            hole: -0.5,
+            survival: 0.0,
+            game_over: -10.0
+        }
+    }
+}
+
+/// Options of a [`TetrisEnv`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnvConfig {
+    /// Settings of every game, the mode decides when an episode ends
+    pub settings: GameSettings,
+    pub rewards: RewardShaping,
+    /// Ticks the game runs for each step, at least 1
+    pub frames_per_step: usize
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            settings: GameSettings::default(),
+            rewards: RewardShaping::default(),
+            frames_per_step: 1
+        }
+    }
+}
+
+/// Game state given to the agent
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    /// Placed squares, hidden buffer included
+    pub board: Board,
+    pub current_type: TetrisType,
+    pub current_rotation: Rotation,
+    /// Shape of the current tetris, already turned
+    pub current_map: TetrisMap,
+    /// Position of the map of the current tetris on the board
+    pub current_position: Position,
+    /// Upcoming tetris, front is the next one
+    pub queue: VecDeque<TetrisType>,
+    pub held: Option<TetrisType>,
+    pub can_hold: bool
+}
+
+impl Observation {
+    /// Visible field as 0 and 1 row after row, with the current tetris drawn in it as 2.
+    /// Flat so it can be fed straight to a neural network
+    pub fn grid(&self) -> Vec<u8> {
+        let width = self.board.width();
+        let mut grid: Vec<u8> = self.board
+            .visible_rows()
+            .flatten()
+            .map(|square| square.is_some() as u8)
+            .collect();
+
+        for (yindex, row) in self.current_map.iter().enumerate() {
+            for (xindex, filled) in row.iter().enumerate() {
+                let x = self.current_position.x + xindex as i32;
+                let y = self.current_position.y + yindex as i32 - BUFFER_HEIGHT as i32;
+                if *filled && x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < self.board.visible_height() {
+                    grid[y as usize * width + x as usize] = 2;
+                }
+            }
+        }
+
+        grid
+    }
+}
+
+/// What happened during a step, besides the reward
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StepInfo {
+    /// Lines cleared during the step
+    pub lines_cleared: usize,
+    /// Holes in the board after the step
+    pub holes: usize,
+    pub score: usize,
+    pub level: usize,
+    pub pieces: usize,
+    pub ticks: u64,
+    /// Every game event of the step
+    pub events: Vec<GameEvent>
+}
+
+/// Reinforcement learning environment, in the style of gym
+///
+/// Each [`TetrisEnv::step`] gives one action then runs the game for `frames_per_step` ticks.
+/// An episode ends when the game is over
+pub struct TetrisEnv {
+    config: EnvConfig,
+    game: TetrisGame,
+    /// Holes before the current step, to reward the difference
+    holes: usize
+}
+
+impl TetrisEnv {
+    /// Makes the environment, call [`TetrisEnv::reset`] to start an episode
+    pub fn new(config: EnvConfig) -> Self {
+        Self {
+            config,
+            game: TetrisGame::with_settings(0, config.settings),
+            holes: 0
+        }
+    }
+
+    /// Game of the current episode
+    pub fn game(&self) -> &TetrisGame {
+        &self.game
+    }
+
+    /// Starts a new episode, the same seed always gives the same tetris order
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = TetrisGame::with_settings(seed, self.config.settings);
+        self.holes = 0;
+        self.observation()
+    }
+
+    /// Gives the action to the game and runs it, returns the observation, the reward,
+    /// true if the episode is over and some details
+    pub fn step(&mut self, action: Action) -> (Observation, f64, bool, StepInfo) {
+        if self.game.is_gameover() {
+            return (self.observation(), 0.0, true, self.info(0, Vec::new()));
+        }
+
+        if let Some(input) = action.input() {
+            self.game.handle_input(input);
+        }
+        for _ in 0..self.config.frames_per_step.max(1) {
+            self.game.tick();
+            if self.game.is_gameover() {
+                break;
+            }
+        }
+        if action == Action::SoftDrop {
+            self.game.handle_input(Input::SoftDropEnd);
+        }
+
+        let events = self.game.drain_events();
+        let rewards = &self.config.rewards;
+        let mut reward = 0.0;
+
+        let mut lines_cleared = 0;
+        for event in &events {
+            if let GameEvent::LinesCleared(lines) = event {
+                lines_cleared += lines;
+                reward += rewards.lines[(*lines).min(4)];
+            }
+        }
+
+        let holes = self.game.board().holes();
+        reward += rewards.hole * (holes as f64 - self.holes as f64);
+        self.holes = holes;
+
+        let done = self.game.is_gameover();
+        if done && !self.game.goal_reached() {
+            reward += rewards.game_over;
+        } else if !done {
+            reward += rewards.survival;
+        }
+
+        (self.observation(), reward, done, self.info(lines_cleared, events))
+    }
+
+    fn observation(&self) -> Observation {
+        let current = self.game.current_tetris();
+        Observation {
+            board: self.game.board().clone(),
+            current_type: current.tetris_type,
+            current_rotation: current.rotation,
+            current_map: current.map,
+            current_position: current.position,
+            queue: self.game.queue().clone(),
+            held: self.game.held_tetris(),
+            can_hold: self.game.can_hold()
+        }
+    }
+
+    fn info(&self, lines_cleared: usize, events: Vec<GameEvent>) -> StepInfo {
+        let stats = self.game.stats();
+        StepInfo {
+            lines_cleared,
+            holes: self.holes,
+            score: stats.score,
+            level: stats.level,
+            pieces: stats.pieces,
+            ticks: stats.ticks,
+            events
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reset_with_a_seed_restarts_the_same_episode() {
+        let mut env = TetrisEnv::new(EnvConfig::default());
+        let first = env.reset(8);
+        let steps: Vec<f64> = (0..300).map(|step| env.step(Action::ALL[step % Action::ALL.len()]).1).collect();
+
+        assert_eq!(env.reset(8), first);
+        let again: Vec<f64> = (0..300).map(|step| env.step(Action::ALL[step % Action::ALL.len()]).1).collect();
+        assert_eq!(steps, again);
+    }
+
+    #[test]
+    fn grid_draws_the_current_tetris() {
+        let mut env = TetrisEnv::new(EnvConfig::default());
+        let observation = env.reset(2);
+        let grid = observation.grid();
+
+        assert_eq!(grid.len(), observation.board.width() * observation.board.visible_height());
+        // Spawned on the first visible row, the rest of it is in the buffer
+        assert!(grid.iter().filter(|square| **square == 2).count() >= 1);
+        assert!(grid.iter().all(|square| *square != 1));
+    }
+
+    #[test]
+    fn episode_ends_with_the_game_over_penalty() {
+        let config = EnvConfig {
+            settings: GameSettings { width: 4, height: 4, ..Default::default() },
+            ..Default::default()
+        };
+        let mut env = TetrisEnv::new(config);
+        env.reset(6);
+
+        let (mut reward, mut done, mut steps) = (0.0, false, 0);
+        while !done {
+            (_, reward, done, _) = env.step(Action::HardDrop);
+            steps += 1;
+            assert!(steps < 100, "never topped out");
+        }
+
+        assert!(reward <= config.rewards.game_over);
+        let (_, reward, done, info) = env.step(Action::HardDrop);
+        assert_eq!((reward, done), (0.0, true));
+        assert!(info.events.is_empty());
+    }
+
+    #[test]
+    fn rewards_follow_the_shaping() {
+        let rewards = RewardShaping { survival: 0.25, ..Default::default() };
+        let mut env = TetrisEnv::new(EnvConfig { rewards, ..Default::default() });
+        env.reset(3);
+        let mut ai = crate::ai::Ai::default();
+
+        // The AI picks the moves, the env plays them
+        let mut holes = 0;
+        let mut lines = 0;
+        while lines < 10 {
+            let action = match ai.next_input(env.game()).expect("the AI topped out") {
+                Input::MoveLeft => Action::MoveLeft,
+                Input::MoveRight => Action::MoveRight,
+                Input::RotateLeft => Action::RotateLeft,
+                Input::RotateRight => Action::RotateRight,
+                Input::HardDrop => Action::HardDrop,
+                input => panic!("unexpected input {:?}", input)
+            };
+
+            let (_, reward, done, info) = env.step(action);
+            assert!(!done);
+            let expected = rewards.lines[info.lines_cleared] + rewards.hole * (info.holes as f64 - holes as f64) + rewards.survival;
+            assert_eq!(reward, expected);
+
+            holes = info.holes;
+            lines += info.lines_cleared;
+        }
+    }
+}
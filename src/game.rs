@@ -0,0 +1,1428 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+use crate::board::{Board, BUFFER_HEIGHT, MAX_BOARD_HEIGHT, MAX_BOARD_WIDTH, MIN_BOARD_HEIGHT, MIN_BOARD_WIDTH};
+use crate::generator::{PieceGenerator, Randomizer};
+use crate::rotation::{RotationSystem, RotationSystemKind};
+use crate::scoring;
+use crate::tetris::{Position, Tetris, TetrisType};
+
+/// Default board width, see [`GameSettings::width`]
+pub const GAME_WIDTH: usize = 10;
+/// Default board height, see [`GameSettings::height`]
+pub const GAME_HEIGHT: usize = 20;
+/// Most upcoming tetris the queue can show
+pub const MAX_QUEUE_LENGTH: usize = 6;
+/// Number of [`TetrisGame::tick`] calls per second the timings are made for
+pub const TICKS_PER_SECOND: usize = 60;
+/// Longest [`GameSettings::lock_delay`]
+pub const MAX_LOCK_DELAY: usize = 2 * TICKS_PER_SECOND;
+/// Longest [`GameSettings::das`]
+pub const MAX_DAS: usize = TICKS_PER_SECOND / 2;
+/// Slowest [`GameSettings::arr`]
+pub const MAX_ARR: usize = TICKS_PER_SECOND / 6;
+/// Fastest [`GameSettings::soft_drop_factor`]
+pub const MAX_SOFT_DROP_FACTOR: usize = 40;
+/// Lines to clear to finish a [`GameMode::Sprint`]
+pub const SPRINT_LINES: usize = 40;
+/// Length of a [`GameMode::Ultra`] game, in ticks
+pub const ULTRA_TICKS: u64 = 2 * 60 * TICKS_PER_SECOND as u64;
+
+/// Things that happened during a game step
+///
+/// Drained by whoever drives the game (renderer, tests, bots...) to know what changed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    /// current_tetris moved by the given shift
+    PieceMoved(Position),
+    /// current_tetris was rotated
+    PieceRotated,
+    /// current_tetris was inserted in the board
+    PieceLocked,
+    /// A new current_tetris was spawned
+    PieceSpawned(TetrisType),
+    /// current_tetris was swapped with the hold slot
+    PieceHeld(TetrisType),
+    /// current_tetris was hard dropped by the given number of rows
+    HardDrop(usize),
+    /// Number of lines cleared at once
+    LinesCleared(usize),
+    /// current_tetris locked as a T-spin, with the number of lines it cleared
+    TSpin(usize),
+    /// New level reached
+    LevelUp(usize),
+    /// Garbage lines sent to the opponent, what is left of the attack after cancelling the pending garbage
+    GarbageSent(usize),
+    /// Pending garbage lines pushed into the board
+    GarbageReceived(usize),
+    /// The goal of the game mode was reached, the game is over
+    GoalReached,
+    GameOver
+}
+
+/// Player actions the game understands
+///
+/// Front-ends translate their own events (keyboard, controller, network...) into these
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// Moves one square left, without auto repeat
+    MoveLeft,
+    /// Moves one square right, without auto repeat
+    MoveRight,
+    /// Moves one square left and keeps moving with DAS/ARR until [`Input::MoveLeftEnd`]
+    MoveLeftStart,
+    MoveLeftEnd,
+    /// Moves one square right and keeps moving with DAS/ARR until [`Input::MoveRightEnd`]
+    MoveRightStart,
+    MoveRightEnd,
+    RotateLeft,
+    RotateRight,
+    /// Starts fast falling until [`Input::SoftDropEnd`]
+    SoftDropStart,
+    SoftDropEnd,
+    /// Swaps current_tetris with the hold slot
+    Hold,
+    /// Drops current_tetris to the bottom and locks it
+    HardDrop
+}
+
+impl Input {
+    /// Every input, replays and network messages find them by their `Debug` name here
+    pub const ALL: [Input; 12] = [
+        Input::MoveLeft, Input::MoveRight, Input::MoveLeftStart, Input::MoveLeftEnd, Input::MoveRightStart, Input::MoveRightEnd,
+        Input::RotateLeft, Input::RotateRight, Input::SoftDropStart, Input::SoftDropEnd, Input::Hold, Input::HardDrop
+    ];
+    /// Lets go of everything that can be held. Give them when the player's keys stop being followed, like on pause,
+    /// as the releases would never reach the game
+    pub const RELEASES: [Input; 3] = [Input::MoveLeftEnd, Input::MoveRightEnd, Input::SoftDropEnd];
+}
+
+/// Decides when a game ends besides topping out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameMode {
+    /// Endless, goes on until the stack reaches the top
+    #[default]
+    Marathon,
+    /// Clear [`SPRINT_LINES`] lines as fast as possible
+    Sprint,
+    /// Score as much as possible in [`ULTRA_TICKS`]
+    Ultra
+}
+
+impl GameMode {
+    /// Every game mode, in menu order
+    pub const ALL: [GameMode; 3] = [GameMode::Marathon, GameMode::Sprint, GameMode::Ultra];
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    /// Parses the names used on the command line: marathon, sprint and ultra
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "marathon" => Ok(GameMode::Marathon),
+            "sprint" => Ok(GameMode::Sprint),
+            "ultra" => Ok(GameMode::Ultra),
+            _ => Err(format!("unknown game mode \"{}\", expected marathon, sprint or ultra", name))
+        }
+    }
+}
+
+/// How a game went, shown on the game over screen and kept in the high scores
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GameStats {
+    /// Seed the game was created with, replays the same tetris order
+    pub seed: u64,
+    pub score: usize,
+    pub lines: usize,
+    pub level: usize,
+    /// Number of tetris locked in the board
+    pub pieces: usize,
+    /// Game length
+    pub ticks: u64,
+    /// True if the game ended by reaching the goal of its mode instead of topping out
+    pub goal_reached: bool
+}
+
+/// What gives the player more time once the tetris touches the ground
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockReset {
+    /// Modern behaviour, every move or rotation restarts the lock delay, up to the given number of times.
+    /// Reaching a new lowest row gives the resets back
+    MoveReset(usize),
+    /// Classic behaviour, the lock delay only restarts when the tetris falls a row
+    StepReset
+}
+
+/// Options chosen at game creation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GameSettings {
+    /// Decides when the game ends
+    pub mode: GameMode,
+    /// Generator used for the tetris order
+    pub randomizer: Randomizer,
+    /// Number of upcoming tetris known in advance. Clamped between 1 and [`MAX_QUEUE_LENGTH`]
+    pub queue_length: usize,
+    /// Kicks used when a rotation is blocked
+    pub rotation_system: RotationSystemKind,
+    /// Ticks a tetris can stay on the ground before locking
+    pub lock_delay: usize,
+    /// Rule restarting the lock delay
+    pub lock_reset: LockReset,
+    /// Level the game starts at, gravity and points depend on it. At most [`scoring::MAX_GRAVITY_LEVEL`]
+    pub start_level: usize,
+    /// Delayed auto shift, ticks a direction must be held before it repeats
+    pub das: usize,
+    /// Auto repeat rate, ticks between each repeated move. 0 moves straight to the wall
+    pub arr: usize,
+    /// How many times faster than gravity the tetris falls while soft dropping
+    pub soft_drop_factor: usize,
+    /// Board width in squares, between [`MIN_BOARD_WIDTH`] and [`MAX_BOARD_WIDTH`]
+    pub width: usize,
+    /// Visible board height in squares, between [`MIN_BOARD_HEIGHT`] and [`MAX_BOARD_HEIGHT`]. The hidden buffer comes on top
+    pub height: usize
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::default(),
+            randomizer: Randomizer::default(),
+            queue_length: 5,
+            rotation_system: RotationSystemKind::default(),
+            lock_delay: TICKS_PER_SECOND / 2,
+            lock_reset: LockReset::MoveReset(15),
+            start_level: 1,
+            das: 10,
+            arr: 2,
+            soft_drop_factor: 20,
+            width: GAME_WIDTH,
+            height: GAME_HEIGHT
+        }
+    }
+}
+
+impl GameSettings {
+    /// Errors on the first setting outside what the settings screen allows, for settings coming from a file or another player
+    pub fn check(&self) -> Result<(), String> {
+        let ranges = [
+            ("queue_length", self.queue_length, 1, MAX_QUEUE_LENGTH),
+            ("lock_delay", self.lock_delay, 0, MAX_LOCK_DELAY),
+            ("start_level", self.start_level, 1, scoring::MAX_GRAVITY_LEVEL),
+            ("das", self.das, 0, MAX_DAS),
+            ("arr", self.arr, 0, MAX_ARR),
+            ("soft_drop_factor", self.soft_drop_factor, 1, MAX_SOFT_DROP_FACTOR),
+            ("width", self.width, MIN_BOARD_WIDTH, MAX_BOARD_WIDTH),
+            ("height", self.height, MIN_BOARD_HEIGHT, MAX_BOARD_HEIGHT)
+        ];
+
+        for (name, value, min, max) in ranges {
+            if !(min..=max).contains(&value) {
+                return Err(format!("{} must be between {} and {}, got {}", name, min, max, value));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// This struct contains all game logic and game states
+///
+/// Does not know anything about rendering, use [`TetrisGame::drain_events`] and the getters to draw it
+pub struct TetrisGame {
+    /// Options the game was created with
+    settings: GameSettings,
+    /// Seed of rng
+    seed: u64,
+    /// Number of ticks since the game started
+    ticks: u64,
+    /// Points from line clears and drops
+    score: usize,
+    /// number of lines cleared
+    lines: usize,
+    /// Current level, goes up every [`scoring::LINES_PER_LEVEL`] lines
+    level: usize,
+    /// Number of line clearing locks in a row, None when the last lock didn't clear anything
+    combo: Option<usize>,
+    /// True if the last line clear was a tetris or a T-spin
+    back_to_back: bool,
+    /// Number of tetris locked in the board
+    pieces: usize,
+    /// True if the game ended by reaching the goal of its mode
+    goal_reached: bool,
+    /// True if the last successful action on current_tetris was a rotation, needed for T-spins
+    last_move_rotation: bool,
+    /// game grid
+    ///
+    /// Only contains the placed squares with thier types. Not the tetris themselves
+    board: Board,
+    /// rng generator
+    rng: SmallRng,
+    /// Decides the order of the tetris
+    generator: Box<dyn PieceGenerator>,
+    /// Decides how blocked rotations are kicked
+    rotation_system: Box<dyn RotationSystem>,
+    /// Acelerates when player is pressing down
+    fast_falling: bool,
+    /// Directions currently held for auto shift, left then right
+    shift_held: [bool; 2],
+    /// Direction auto shift moves to, -1 for left, 1 for right, 0 when nothing is held
+    shift_direction: i32,
+    /// Ticks the shift direction has been held
+    das_counter: usize,
+    /// Ticks since the last auto repeat move
+    arr_counter: usize,
+    /// Counter for time passing
+    refresh_counter: usize,
+    /// Contains the struct of the current falling tetris
+    current_tetris: Tetris,
+    /// Upcoming tetris, front is the next one
+    queue: VecDeque<TetrisType>,
+    /// Tetris put aside by the player
+    held_tetris: Option<TetrisType>,
+    /// Hold can only be used once per tetris, reset when a tetris locks
+    hold_used: bool,
+    /// Ticks current_tetris spent on the ground since the last lock delay reset
+    lock_timer: usize,
+    /// Lock delay resets used by current_tetris
+    lock_resets: usize,
+    /// Lowest row current_tetris reached
+    lowest_row: i32,
+    /// Garbage waiting to be pushed into the board, lines and hole column of each attack
+    pending_garbage: VecDeque<(usize, usize)>,
+    /// When true main loop will exit game
+    gameover: bool,
+    /// Events since the last drain
+    events: Vec<GameEvent>
+}
+
+impl TetrisGame {
+    /// Creates a new TetrisGame object with the default settings
+    pub fn new(seed: u64) -> Self {
+        Self::with_settings(seed, GameSettings::default())
+    }
+
+    /// Creates a new TetrisGame object
+    pub fn with_settings(seed: u64, settings: GameSettings) -> Self {
+        Self::with_generator(seed, settings, settings.randomizer.generator())
+    }
+
+    /// Creates a new TetrisGame object with the piece generator of your choice
+    ///
+    /// `settings.randomizer` is ignored
+    pub fn with_generator(seed: u64, settings: GameSettings, mut generator: Box<dyn PieceGenerator>) -> Self {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let first_tetris = generator.next_tetris(&mut rng);
+        let queue = (0..settings.queue_length.clamp(1, MAX_QUEUE_LENGTH))
+            .map(|_| generator.next_tetris(&mut rng))
+            .collect();
+
+        let board = Board::new(settings.width, settings.height);
+        let current_tetris = Self::spawned_tetris(first_tetris, &board);
+
+        Self {
+            settings,
+            seed,
+            ticks: 0,
+            score: 0,
+            lines: 0,
+            level: scoring::level_for_lines(settings.start_level, 0),
+            combo: None,
+            back_to_back: false,
+            pieces: 0,
+            goal_reached: false,
+            last_move_rotation: false,
+            board,
+            rng,
+            generator,
+            rotation_system: settings.rotation_system.system(),
+            fast_falling: false,
+            shift_held: [false; 2],
+            shift_direction: 0,
+            das_counter: 0,
+            arr_counter: 0,
+            refresh_counter: 0,
+            current_tetris,
+            queue,
+            held_tetris: None,
+            hold_used: false,
+            lock_timer: 0,
+            lock_resets: 0,
+            lowest_row: current_tetris.position.y,
+            pending_garbage: VecDeque::new(),
+            gameover: false,
+            events: Vec::new()
+        }
+    }
+
+    /// Options the game was created with
+    pub fn settings(&self) -> &GameSettings {
+        &self.settings
+    }
+
+    /// Seed the game was created with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Number of ticks since the game started
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Points from line clears and drops
+    pub fn score(&self) -> usize {
+        self.score
+    }
+
+    /// Number of lines cleared
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Current level
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Number of line clearing locks in a row, None when the last lock didn't clear anything
+    pub fn combo(&self) -> Option<usize> {
+        self.combo
+    }
+
+    /// True if the next tetris or T-spin clear gets the back-to-back bonus
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    /// True if the game ended by reaching the goal of its mode instead of topping out
+    pub fn goal_reached(&self) -> bool {
+        self.goal_reached
+    }
+
+    /// Summary of the game so far
+    pub fn stats(&self) -> GameStats {
+        GameStats {
+            seed: self.seed,
+            score: self.score,
+            lines: self.lines,
+            level: self.level,
+            pieces: self.pieces,
+            ticks: self.ticks,
+            goal_reached: self.goal_reached
+        }
+    }
+
+    /// Placed squares, indexed as `board[y][x]`
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The falling tetris
+    pub fn current_tetris(&self) -> &Tetris {
+        &self.current_tetris
+    }
+
+    /// Upcoming tetris, the first one is the next to spawn
+    pub fn queue(&self) -> &VecDeque<TetrisType> {
+        &self.queue
+    }
+
+    /// Tetris in the hold slot
+    pub fn held_tetris(&self) -> Option<TetrisType> {
+        self.held_tetris
+    }
+
+    /// False if hold was already used for the current tetris
+    pub fn can_hold(&self) -> bool {
+        !self.hold_used
+    }
+
+    /// Garbage lines waiting to be pushed into the board
+    pub fn pending_garbage(&self) -> usize {
+        self.pending_garbage.iter().map(|(lines, _)| lines).sum()
+    }
+
+    /// Queues garbage from the opponent. It is pushed into the board when a tetris locks without clearing lines,
+    /// clearing lines cancels it first
+    pub fn receive_garbage(&mut self, lines: usize, hole: usize) {
+        if lines > 0 && !self.gameover {
+            self.pending_garbage.push_back((lines, hole));
+        }
+    }
+
+    /// True once the stack reached the top, the game ignores everything after that
+    pub fn is_gameover(&self) -> bool {
+        self.gameover
+    }
+
+    pub fn set_fast_falling(&mut self, fast_falling: bool) {
+        self.fast_falling = fast_falling;
+    }
+
+    /// Applies a player action
+    ///
+    /// Returns false if the action was refused (blocked move or rotation)
+    pub fn handle_input(&mut self, input: Input) -> bool {
+        if self.gameover {
+            return false;
+        }
+
+        match input {
+            Input::MoveLeft => self.move_tetris_with_check(Position { x: -1, y: 0 }),
+            Input::MoveRight => self.move_tetris_with_check(Position { x: 1, y: 0 }),
+            Input::MoveLeftStart => self.start_shift(-1),
+            Input::MoveLeftEnd => self.end_shift(-1),
+            Input::MoveRightStart => self.start_shift(1),
+            Input::MoveRightEnd => self.end_shift(1),
+            Input::RotateLeft => self.rotate_tetris_left(),
+            Input::RotateRight => self.rotate_tetris_right(),
+            Input::SoftDropStart => {
+                self.set_fast_falling(true);
+                true
+            },
+            Input::SoftDropEnd => {
+                self.set_fast_falling(false);
+                true
+            },
+            Input::Hold => self.hold_tetris(),
+            Input::HardDrop => {
+                self.hard_drop();
+                true
+            }
+        }
+    }
+
+    /// Takes every event that happened since the last call
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Index of a direction in shift_held
+    fn shift_index(direction: i32) -> usize {
+        if direction < 0 { 0 } else { 1 }
+    }
+
+    /// Moves once in the direction and starts charging DAS. The last pressed direction wins
+    fn start_shift(&mut self, direction: i32) -> bool {
+        self.shift_held[Self::shift_index(direction)] = true;
+        self.shift_direction = direction;
+        self.das_counter = 0;
+        self.arr_counter = 0;
+        self.move_tetris_with_check(Position { x: direction, y: 0 })
+    }
+
+    /// Stops auto shift in the direction, falls back to the other one if it is still held
+    fn end_shift(&mut self, direction: i32) -> bool {
+        self.shift_held[Self::shift_index(direction)] = false;
+
+        if self.shift_direction == direction {
+            self.shift_direction = if self.shift_held[Self::shift_index(-direction)] { -direction } else { 0 };
+            self.das_counter = 0;
+            self.arr_counter = 0;
+        }
+        true
+    }
+
+    /// Repeats the held direction once DAS is charged, every ARR ticks
+    fn update_auto_shift(&mut self) {
+        if self.shift_direction == 0 {
+            return;
+        }
+
+        let shift = Position { x: self.shift_direction, y: 0 };
+        self.das_counter = (self.das_counter + 1).min(self.settings.das);
+        if self.das_counter < self.settings.das {
+            return;
+        }
+
+        if self.settings.arr == 0 {
+            while self.move_tetris_with_check(shift) {}
+            return;
+        }
+
+        // First repeat happens as soon as DAS is charged
+        if self.arr_counter == 0 || self.arr_counter >= self.settings.arr {
+            self.move_tetris_with_check(shift);
+            self.arr_counter = 0;
+        }
+        self.arr_counter += 1;
+    }
+
+    /// Spawns the next tetris of the queue and refills it from the generator
+    fn make_new_random_tetris(&mut self) {
+        let tetris = self.queue.pop_front().unwrap_or_else(|| self.generator.next_tetris(&mut self.rng));
+        self.queue.push_back(self.generator.next_tetris(&mut self.rng));
+
+        self.spawn_tetris(tetris);
+    }
+
+    /// Makes a tetris at the spawn position, resting on the bottom row of the buffer.
+    /// Centered on the board and pushed away from the walls on thin boards.
+    /// Then drops it one row right away when nothing is in the way, like the guideline asks
+    fn spawned_tetris(tetris_type: TetrisType, board: &Board) -> Tetris {
+        let mut tetris = Tetris::new(tetris_type);
+        let most_left = tetris.most_left().unwrap_or_default() as i32;
+        let most_right = tetris.most_right().unwrap_or_default() as i32;
+        let most_bottom = tetris.most_bottom().unwrap_or_default() as i32;
+
+        tetris.position.x = (board.width() as i32 / 2 - 2)
+            .min(board.width() as i32 - 1 - most_right)
+            .max(-most_left);
+        tetris.position.y = BUFFER_HEIGHT as i32 - 1 - most_bottom;
+
+        let mut dropped = tetris;
+        dropped.position.y += 1;
+        if board.fits(&tetris) && board.fits(&dropped) { dropped } else { tetris }
+    }
+
+    /// Replaces current_tetris by a fresh one at the spawn position
+    ///
+    /// Block out: the game is over if it overlaps the stack
+    fn spawn_tetris(&mut self, tetris_type: TetrisType) {
+        self.current_tetris = Self::spawned_tetris(tetris_type, &self.board);
+        if !self.fits(&self.current_tetris) {
+            self.top_out();
+        }
+        self.lock_timer = 0;
+        self.lock_resets = 0;
+        self.lowest_row = self.current_tetris.position.y;
+        self.last_move_rotation = false;
+        self.events.push(GameEvent::PieceSpawned(tetris_type));
+    }
+
+    /// Swaps current_tetris with the held one, or with the next one if nothing is held
+    ///
+    /// The new current_tetris respawns at the top with its rotation reset.
+    /// Only works once until the tetris locks, and not after game over
+    pub fn hold_tetris(&mut self) -> bool {
+        if self.hold_used || self.gameover {
+            return false;
+        }
+
+        let current_type = self.current_tetris.tetris_type;
+        match self.held_tetris.replace(current_type) {
+            Some(held) => self.spawn_tetris(held),
+            None => self.make_new_random_tetris()
+        }
+
+        self.hold_used = true;
+        self.events.push(GameEvent::PieceHeld(current_type));
+        true
+    }
+
+    /// Advances the game by one frame. Manages game speed, fastfall, auto shift and lock delay
+    ///
+    /// Gravity follows the level, see [`scoring::gravity_ticks`]
+    pub fn tick(&mut self) {
+        if self.gameover {
+            return;
+        }
+
+        let refresh_speed = {
+            if self.fast_falling {
+                (scoring::gravity_ticks(self.level) / self.settings.soft_drop_factor.max(1)).max(1)
+            } else {
+                scoring::gravity_ticks(self.level)
+            }
+        };
+
+        self.ticks += 1;
+        if self.settings.mode == GameMode::Ultra && self.ticks >= ULTRA_TICKS {
+            self.reach_goal();
+            return;
+        }
+
+        self.update_auto_shift();
+
+        self.refresh_counter += 1;
+        if refresh_speed <= self.refresh_counter {
+            self.update();
+            self.refresh_counter = 0;
+        }
+
+        // Lock once the tetris stayed on the ground long enough
+        if self.is_grounded() {
+            self.lock_timer += 1;
+            if self.lock_timer >= self.settings.lock_delay {
+                self.lock_tetris();
+            }
+        }
+    }
+
+    fn update(&mut self) {
+        // Locking is handled by the lock delay
+        if !self.is_grounded() && self.move_tetris_with_check(Position { x: 0, y: 1 }) && self.fast_falling {
+            self.score += scoring::SOFT_DROP_POINTS;
+        }
+    }
+
+    /// True if current_tetris can't fall any lower
+    pub fn is_grounded(&self) -> bool {
+        self.check_tetris_hit_bottom(&self.current_tetris, 1) || self.check_tetris_hit_board(&self.current_tetris, Position { x: 0, y: 1 })
+    }
+
+    /// Applies the lock reset rule after current_tetris moved or rotated
+    fn reset_lock_delay(&mut self, was_grounded: bool) {
+        let new_lowest_row = self.current_tetris.position.y > self.lowest_row;
+        if new_lowest_row {
+            self.lowest_row = self.current_tetris.position.y;
+        }
+
+        match self.settings.lock_reset {
+            LockReset::MoveReset(max_resets) => {
+                if new_lowest_row {
+                    self.lock_timer = 0;
+                    self.lock_resets = 0;
+                } else if was_grounded && self.lock_resets < max_resets {
+                    self.lock_timer = 0;
+                    self.lock_resets += 1;
+                }
+            },
+            LockReset::StepReset => {
+                if new_lowest_row {
+                    self.lock_timer = 0;
+                }
+            }
+        }
+    }
+
+    /// Inserts current_tetris in the board, clears the full lines and spawns the next tetris
+    fn lock_tetris(&mut self) {
+        let tspin = self.is_tspin();
+        self.insert_tetris_in_map();
+
+        let mut cleared_lines = 0;
+        for (index, full) in self.full_lines().into_iter().enumerate() {
+            if full {
+                self.board.clear_line(index);
+                cleared_lines += 1;
+            }
+        }
+
+        self.pieces += 1;
+        let attack = self.score_lock(cleared_lines, tspin);
+        if cleared_lines > 0 {
+            self.send_garbage(attack);
+        } else {
+            self.add_pending_garbage();
+        }
+
+        if self.settings.mode == GameMode::Sprint && self.lines >= SPRINT_LINES {
+            self.reach_goal();
+        }
+
+        self.make_new_random_tetris();
+        self.hold_used = false;
+        self.refresh_counter = 0;
+    }
+
+    /// Cancels pending garbage with the attack, what is left goes to the opponent
+    fn send_garbage(&mut self, mut attack: usize) {
+        while attack > 0 {
+            let Some((lines, _)) = self.pending_garbage.front_mut() else {
+                break;
+            };
+
+            let cancelled = attack.min(*lines);
+            *lines -= cancelled;
+            attack -= cancelled;
+            if *lines == 0 {
+                self.pending_garbage.pop_front();
+            }
+        }
+
+        if attack > 0 {
+            self.events.push(GameEvent::GarbageSent(attack));
+        }
+    }
+
+    /// Pushes every pending garbage into the board, tops out if the stack goes over the top
+    fn add_pending_garbage(&mut self) {
+        let mut overflow = false;
+        while let Some((lines, hole)) = self.pending_garbage.pop_front() {
+            overflow |= self.board.add_garbage(lines, hole.min(self.board.width() - 1));
+            self.events.push(GameEvent::GarbageReceived(lines));
+        }
+
+        if overflow {
+            self.top_out();
+        }
+    }
+
+    /// Ends the game as a loss
+    fn top_out(&mut self) {
+        if self.gameover {
+            return;
+        }
+
+        self.gameover = true;
+        self.events.push(GameEvent::GameOver);
+    }
+
+    /// Ends the game as a win
+    fn reach_goal(&mut self) {
+        if self.gameover {
+            return;
+        }
+
+        self.gameover = true;
+        self.goal_reached = true;
+        self.events.push(GameEvent::GoalReached);
+    }
+
+    /// Adds the points of a lock and updates lines, level, combo and back-to-back
+    ///
+    /// Points use the level from before the clear. Returns the garbage lines the clear sends in versus
+    fn score_lock(&mut self, cleared_lines: usize, tspin: bool) -> usize {
+        let mut points = scoring::line_clear_points(cleared_lines, tspin) * self.level;
+        let mut attack = 0;
+
+        if cleared_lines > 0 {
+            let difficult = scoring::is_difficult_clear(cleared_lines, tspin);
+            let back_to_back = difficult && self.back_to_back;
+            if back_to_back {
+                points = points * 3 / 2;
+            }
+            self.back_to_back = difficult;
+
+            let combo = self.combo.map_or(0, |combo| combo + 1);
+            points += scoring::COMBO_POINTS * combo * self.level;
+            self.combo = Some(combo);
+
+            attack = scoring::garbage_lines(cleared_lines, tspin, back_to_back, combo);
+
+            self.lines += cleared_lines;
+            self.events.push(GameEvent::LinesCleared(cleared_lines));
+        } else {
+            self.combo = None;
+        }
+
+        if tspin {
+            self.events.push(GameEvent::TSpin(cleared_lines));
+        }
+
+        self.score += points;
+
+        let level = scoring::level_for_lines(self.settings.start_level, self.lines);
+        if level != self.level {
+            self.level = level;
+            self.events.push(GameEvent::LevelUp(level));
+        }
+
+        attack
+    }
+
+    /// Checks the 3 corner rule: current_tetris is a T, its last move was a rotation
+    /// and at least 3 of the squares diagonal to its center are taken
+    fn is_tspin(&self) -> bool {
+        if self.current_tetris.tetris_type != TetrisType::TShape || !self.last_move_rotation {
+            return false;
+        }
+
+        // Maps rotate around their center square (2, 2)
+        let taken_corners = [(1, 1), (3, 1), (1, 3), (3, 3)]
+            .into_iter()
+            .filter(|(xindex, yindex)| {
+                self.board.is_blocked(self.current_tetris.position.x + xindex, self.current_tetris.position.y + yindex)
+            })
+            .count();
+
+        taken_corners >= 3
+    }
+
+    /// Number of rows current_tetris can fall before landing
+    pub fn drop_distance(&self) -> usize {
+        let mut distance = 0;
+        while !self.check_tetris_hit_bottom(&self.current_tetris, distance + 1) &&
+            !self.check_tetris_hit_board(&self.current_tetris, Position { x: 0, y: distance + 1 }) {
+            distance += 1;
+        }
+
+        distance as usize
+    }
+
+    /// Where current_tetris would land if dropped now
+    pub fn ghost_tetris(&self) -> Tetris {
+        let mut ghost = self.current_tetris;
+        ghost.position.y += self.drop_distance() as i32;
+        ghost
+    }
+
+    /// Moves current_tetris to the lowest legal row and locks it right away. Does nothing after game over
+    pub fn hard_drop(&mut self) {
+        if self.gameover {
+            return;
+        }
+
+        let distance = self.drop_distance();
+        self.current_tetris.position.y += distance as i32;
+        if distance > 0 {
+            self.last_move_rotation = false;
+        }
+        self.score += distance * scoring::HARD_DROP_POINTS;
+        self.events.push(GameEvent::HardDrop(distance));
+        self.lock_tetris();
+    }
+
+    /// Check if tetris will hit board when shifted
+    pub fn check_tetris_hit_board(&self, tetris: &Tetris, shift: Position) -> bool {
+        let mut colided = false;
+        for (yindex, y) in tetris.map.into_iter().enumerate() {
+            for (xindex, x) in y.into_iter().enumerate() {
+                if x &&
+                    (yindex as i32 + shift.y + tetris.position.y) >= 0 &&
+                    (yindex as i32 + shift.y + tetris.position.y) < self.board.height() as i32 &&
+                    (xindex as i32 + shift.x + tetris.position.x) >= 0 &&
+                    (xindex as i32 + shift.x + tetris.position.x) < self.board.width() as i32 &&
+                    self.board[(yindex as i32 + shift.y + tetris.position.y) as usize][(xindex as i32 + shift.x + tetris.position.x) as usize].is_some() {
+                    colided = true;
+                }
+            }
+        }
+
+        colided
+    }
+
+    /// Check if tetris is hitting the board's walls
+    pub fn check_tetris_hit_wall(&self, tetris: &Tetris, shift: i32) -> bool {
+        tetris.position.x + tetris.most_left().unwrap_or_default() as i32 + shift < 0 ||
+        tetris.position.x + tetris.most_right().unwrap_or_default() as i32 + shift >= self.board.width() as i32
+    }
+
+    /// Check if tetris reached the bottom of the board
+    pub fn check_tetris_hit_bottom(&self, tetris: &Tetris, shift: i32) -> bool {
+        tetris.position.y + tetris.most_bottom().unwrap() as i32 + shift >= self.board.height() as i32
+    }
+
+    /// Move the tetris
+    ///
+    /// Also check if the movement will hit something and cancels if it will something.
+    /// Returns true if the tetris moved, never after game over
+    pub fn move_tetris_with_check(&mut self, shift: Position) -> bool {
+        if self.gameover {
+            return false;
+        }
+        if self.check_tetris_hit_board(&self.current_tetris, shift) || self.check_tetris_hit_wall(&self.current_tetris, shift.x) {
+            return false;
+        }
+
+        let was_grounded = self.is_grounded();
+        self.current_tetris.position.x += shift.x;
+        self.current_tetris.position.y += shift.y;
+        self.reset_lock_delay(was_grounded);
+        self.last_move_rotation = false;
+        self.events.push(GameEvent::PieceMoved(shift));
+        true
+    }
+
+    /// Rotates the tetris left
+    ///
+    /// Tries the kicks of the rotation system and cancels the action if they all hit something.
+    /// Returns true if the tetris rotated, never after game over
+    ///
+    /// Note: If you need to rotate without checks use the tetris struct directly
+    pub fn rotate_tetris_left(&mut self) -> bool {
+        let mut rotated_tetris = self.current_tetris;
+        rotated_tetris.rotate_left();
+
+        self.try_rotate(rotated_tetris)
+    }
+
+    /// Rotates the tetris right
+    ///
+    /// Tries the kicks of the rotation system and cancels the action if they all hit something.
+    /// Returns true if the tetris rotated, never after game over
+    ///
+    /// Note: If you need to rotate without checks use the tetris struct directly
+    pub fn rotate_tetris_right(&mut self) -> bool {
+        let mut rotated_tetris = self.current_tetris;
+        rotated_tetris.rotate_right();
+
+        self.try_rotate(rotated_tetris)
+    }
+
+    /// Replaces current_tetris by its rotated version, shifted by the first kick that doesn't hit anything
+    fn try_rotate(&mut self, rotated_tetris: Tetris) -> bool {
+        if self.gameover {
+            return false;
+        }
+        if !self.fits(&rotated_tetris) && !self.rotation_system.can_kick(&rotated_tetris, &self.board) {
+            return false;
+        }
+
+        let kicks = self.rotation_system.kicks(&rotated_tetris.tetris_type, self.current_tetris.rotation, rotated_tetris.rotation);
+        for kick in kicks {
+            let mut kicked_tetris = rotated_tetris;
+            kicked_tetris.position.x += kick.x;
+            kicked_tetris.position.y += kick.y;
+
+            if self.fits(&kicked_tetris) {
+                let was_grounded = self.is_grounded();
+                self.current_tetris = kicked_tetris;
+                self.reset_lock_delay(was_grounded);
+                self.last_move_rotation = true;
+                self.events.push(GameEvent::PieceRotated);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Check if tetris can be at its position without hitting the board, walls or bottom
+    pub fn fits(&self, tetris: &Tetris) -> bool {
+        self.board.fits(tetris)
+    }
+
+    /// Insert current_tetris inside board
+    ///
+    /// Lock out: the game is over if every square of it is in the hidden buffer
+    fn insert_tetris_in_map(&mut self) {
+        let mut visible = false;
+        for (yindex, y) in self.current_tetris.map.into_iter().enumerate() {
+            for (xindex, x) in y.into_iter().enumerate() {
+                if x &&
+                    yindex as i32 + self.current_tetris.position.y >= 0 &&
+                    yindex as i32 + self.current_tetris.position.y < self.board.height() as i32 &&
+                    xindex as i32 + self.current_tetris.position.x >= 0 &&
+                    xindex as i32 + self.current_tetris.position.x < self.board.width() as i32 {
+                    self.board[(yindex as i32 + self.current_tetris.position.y) as usize][(xindex as i32 + self.current_tetris.position.x) as usize] = Some(self.current_tetris.tetris_type);
+                }
+
+                if x && !self.board.is_hidden(yindex as i32 + self.current_tetris.position.y) {
+                    visible = true;
+                }
+            }
+        }
+        self.events.push(GameEvent::PieceLocked);
+
+        if !visible {
+            self.top_out();
+        }
+    }
+
+    /// Finds all of the full lines in boards.
+    ///
+    /// Return the false or true for each line.
+    /// True if the line is full
+    pub fn full_lines(&self) -> Vec<bool> {
+        (0..self.board.height())
+            .map(|index| self.board.is_line_full(index))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ticks until the tetris locks, or panics if it never does
+    fn tick_until_locked(game: &mut TetrisGame) {
+        for _ in 0..10_000 {
+            game.tick();
+            if game.drain_events().contains(&GameEvent::PieceLocked) {
+                return;
+            }
+        }
+        panic!("the tetris never locked");
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_game() {
+        let mut first = TetrisGame::new(42);
+        let mut second = TetrisGame::new(42);
+
+        for tick in 0..2000 {
+            if tick % 30 == 0 {
+                let input = Input::ALL[tick / 30 % Input::ALL.len()];
+                first.handle_input(input);
+                second.handle_input(input);
+            }
+            first.tick();
+            second.tick();
+        }
+
+        assert_eq!(first.board(), second.board());
+        assert_eq!(first.stats(), second.stats());
+        assert_eq!(first.queue(), second.queue());
+    }
+
+    #[test]
+    fn tetris_falls_one_row_per_gravity_step() {
+        let mut game = TetrisGame::new(1);
+        let start = game.current_tetris().position.y;
+
+        for _ in 0..scoring::gravity_ticks(1) {
+            game.tick();
+        }
+
+        assert_eq!(game.current_tetris().position.y, start + 1);
+        assert!(game.drain_events().contains(&GameEvent::PieceMoved(Position { x: 0, y: 1 })));
+    }
+
+    #[test]
+    fn locked_tetris_stays_in_the_board() {
+        let mut game = TetrisGame::new(7);
+        let tetris_type = game.current_tetris().tetris_type;
+
+        tick_until_locked(&mut game);
+
+        let squares = game.board().iter().flatten().filter(|square| **square == Some(tetris_type)).count();
+        assert_eq!(squares, 4);
+        assert_eq!(game.stats().pieces, 1);
+    }
+
+    #[test]
+    fn queue_feeds_the_next_tetris() {
+        let mut game = TetrisGame::new(3);
+        assert_eq!(game.queue().len(), GameSettings::default().queue_length);
+
+        let next = game.queue()[0];
+        tick_until_locked(&mut game);
+
+        assert_eq!(game.current_tetris().tetris_type, next);
+        assert_eq!(game.queue().len(), GameSettings::default().queue_length);
+    }
+
+    #[test]
+    fn default_settings_pass_the_check() {
+        assert_eq!(GameSettings::default().check(), Ok(()));
+        assert!(GameSettings { start_level: 0, ..Default::default() }.check().is_err());
+        assert!(GameSettings { width: MAX_BOARD_WIDTH + 1, ..Default::default() }.check().is_err());
+    }
+
+    #[test]
+    fn queue_length_is_clamped() {
+        for (queue_length, expected) in [(0, 1), (3, 3), (100, MAX_QUEUE_LENGTH)] {
+            let game = TetrisGame::with_settings(3, GameSettings { queue_length, ..Default::default() });
+            assert_eq!(game.queue().len(), expected);
+        }
+    }
+
+    #[test]
+    fn hold_swaps_once_per_tetris() {
+        let mut game = TetrisGame::new(5);
+        let first = game.current_tetris().tetris_type;
+        let next = game.queue()[0];
+
+        assert!(game.handle_input(Input::Hold));
+        assert_eq!(game.held_tetris(), Some(first));
+        assert_eq!(game.current_tetris().tetris_type, next);
+        assert!(!game.can_hold());
+        assert!(!game.handle_input(Input::Hold));
+
+        tick_until_locked(&mut game);
+        let current = game.current_tetris().tetris_type;
+        assert!(game.handle_input(Input::Hold));
+        assert_eq!(game.held_tetris(), Some(current));
+        assert_eq!(game.current_tetris().tetris_type, first);
+        assert_eq!(game.current_tetris().rotation, crate::rotation::Rotation::Spawn);
+    }
+
+    #[test]
+    fn hard_drop_ghost_and_points() {
+        let mut game = TetrisGame::new(11);
+        let ghost = game.ghost_tetris();
+        let distance = game.drop_distance();
+        assert_eq!(ghost.position.y, game.current_tetris().position.y + distance as i32);
+
+        game.hard_drop();
+        let events = game.drain_events();
+        assert!(events.contains(&GameEvent::HardDrop(distance)));
+        assert!(events.contains(&GameEvent::PieceLocked));
+        assert_eq!(game.score(), distance * scoring::HARD_DROP_POINTS);
+    }
+
+    #[test]
+    fn nothing_moves_after_game_over() {
+        let mut game = TetrisGame::with_settings(11, GameSettings { width: 4, height: 4, ..Default::default() });
+        while !game.is_gameover() {
+            game.hard_drop();
+        }
+
+        let board = game.board().clone();
+        let tetris = *game.current_tetris();
+        let stats = game.stats();
+
+        game.hard_drop();
+        assert!(!game.hold_tetris());
+        assert!(!game.move_tetris_with_check(Position { x: 1, y: 0 }));
+        assert!(!game.rotate_tetris_left());
+        assert!(!game.rotate_tetris_right());
+        for input in Input::ALL {
+            assert!(!game.handle_input(input));
+        }
+        game.tick();
+
+        assert_eq!(game.board(), &board);
+        assert_eq!(game.current_tetris().position, tetris.position);
+        assert_eq!(game.current_tetris().map, tetris.map);
+        assert_eq!(game.stats(), stats);
+    }
+
+    /// Game whose first tetris already sits on the floor
+    fn grounded_game(lock_reset: LockReset) -> TetrisGame {
+        let mut game = TetrisGame::with_settings(13, GameSettings { lock_delay: 30, lock_reset, ..Default::default() });
+        game.current_tetris.position.y += game.drop_distance() as i32;
+        game.lowest_row = game.current_tetris.position.y;
+        assert!(game.is_grounded());
+        game
+    }
+
+    /// Ticks `ticks` times, returns true if the tetris locked
+    fn tick_and_lock(game: &mut TetrisGame, ticks: usize) -> bool {
+        (0..ticks).any(|_| {
+            game.tick();
+            game.drain_events().contains(&GameEvent::PieceLocked)
+        })
+    }
+
+    #[test]
+    fn move_reset_restarts_the_lock_delay_15_times() {
+        let mut game = grounded_game(LockReset::MoveReset(15));
+
+        for reset in 0..15 {
+            assert!(!tick_and_lock(&mut game, 29), "locked before reset {}", reset);
+            let input = if reset % 2 == 0 { Input::MoveLeft } else { Input::MoveRight };
+            assert!(game.handle_input(input));
+        }
+
+        // Out of resets, moving doesn't give more time
+        assert!(!tick_and_lock(&mut game, 20));
+        assert!(game.handle_input(Input::MoveLeft));
+        assert!(!tick_and_lock(&mut game, 9));
+        assert!(tick_and_lock(&mut game, 1));
+    }
+
+    /// Lifts the grounded tetris one row and fills the bottom row under it
+    fn lift_on_a_step(game: &mut TetrisGame) {
+        game.current_tetris.position.y -= 1;
+        game.lowest_row = game.current_tetris.position.y;
+        let bottom = game.board.height() - 1;
+        game.board[bottom].fill(Some(TetrisType::Garbage));
+        assert!(game.is_grounded());
+    }
+
+    /// Empties the bottom row and lets the tetris fall on the floor
+    fn step_down(game: &mut TetrisGame) {
+        let bottom = game.board.height() - 1;
+        game.board[bottom].fill(None);
+        assert!(game.move_tetris_with_check(Position { x: 0, y: 1 }));
+    }
+
+    #[test]
+    fn move_reset_gives_resets_back_on_a_new_lowest_row() {
+        let mut game = grounded_game(LockReset::MoveReset(1));
+        lift_on_a_step(&mut game);
+
+        assert!(!tick_and_lock(&mut game, 29));
+        assert!(game.handle_input(Input::MoveLeft));
+        assert!(!tick_and_lock(&mut game, 29));
+        assert!(game.handle_input(Input::MoveRight));
+
+        // Out of resets with the lock delay almost over, falling a row gives both back
+        step_down(&mut game);
+        assert!(!tick_and_lock(&mut game, 29));
+        assert!(game.handle_input(Input::MoveLeft));
+        assert!(!tick_and_lock(&mut game, 29));
+        assert!(tick_and_lock(&mut game, 1));
+    }
+
+    #[test]
+    fn step_reset_ignores_moves_on_the_ground() {
+        let mut game = grounded_game(LockReset::StepReset);
+
+        assert!(!tick_and_lock(&mut game, 20));
+        assert!(game.handle_input(Input::MoveLeft));
+        assert!(game.handle_input(Input::RotateRight));
+        assert!(!tick_and_lock(&mut game, 9));
+        assert!(tick_and_lock(&mut game, 1));
+    }
+
+    #[test]
+    fn step_reset_restarts_the_lock_delay_when_falling_a_row() {
+        let mut game = grounded_game(LockReset::StepReset);
+        lift_on_a_step(&mut game);
+
+        assert!(!tick_and_lock(&mut game, 20));
+        step_down(&mut game);
+        assert!(!tick_and_lock(&mut game, 29));
+        assert!(tick_and_lock(&mut game, 1));
+    }
+
+    /// Fills the bottom rows but the column, so a line standing there clears them
+    fn fill_well(game: &mut TetrisGame, rows: usize, column: usize) {
+        let height = game.board.height();
+        for y in height - rows..height {
+            for x in 0..game.board.width() {
+                game.board[y][x] = (x != column).then_some(TetrisType::Garbage);
+            }
+        }
+    }
+
+    /// Hard drops a standing line into the well, returns how far it fell
+    fn drop_line_in_well(game: &mut TetrisGame, column: usize) -> usize {
+        game.current_tetris = Tetris::new(TetrisType::Line);
+        game.current_tetris.rotate_right();
+        game.current_tetris.position = Position { x: column as i32 - 2, y: BUFFER_HEIGHT as i32 - 2 };
+        let distance = game.drop_distance();
+        game.hard_drop();
+        distance
+    }
+
+    #[test]
+    fn back_to_back_and_combo_add_points() {
+        let mut game = TetrisGame::new(17);
+        let mut expected = 0;
+
+        for clear in 0..3 {
+            fill_well(&mut game, 4, 0);
+            let distance = drop_line_in_well(&mut game, 0);
+
+            // Tetris at level 1, half more when back-to-back, then the combo bonus
+            let back_to_back = if clear > 0 { 400 } else { 0 };
+            expected += distance * scoring::HARD_DROP_POINTS + 800 + back_to_back + scoring::COMBO_POINTS * clear;
+            assert_eq!(game.score(), expected, "clear {}", clear);
+            assert!(game.back_to_back());
+            assert_eq!(game.combo(), Some(clear));
+        }
+
+        // A lock without lines ends the combo, not back-to-back
+        game.hard_drop();
+        assert_eq!(game.combo(), None);
+        assert!(game.back_to_back());
+    }
+
+    #[test]
+    fn level_stops_at_the_last_one() {
+        let mut game = TetrisGame::with_settings(17, GameSettings { start_level: scoring::MAX_GRAVITY_LEVEL, ..Default::default() });
+        for _ in 0..5 {
+            fill_well(&mut game, 4, 0);
+            drop_line_in_well(&mut game, 0);
+        }
+
+        assert_eq!(game.lines(), 20);
+        assert_eq!(game.level(), scoring::MAX_GRAVITY_LEVEL);
+        assert!(!game.drain_events().iter().any(|event| matches!(event, GameEvent::LevelUp(_))));
+    }
+
+    #[test]
+    fn auto_shift_waits_for_das_then_repeats_every_arr() {
+        let mut game = TetrisGame::with_settings(19, GameSettings { das: 10, arr: 2, ..Default::default() });
+        let start = game.current_tetris().position.x;
+        let shifted = |game: &TetrisGame| game.current_tetris().position.x - start;
+
+        assert!(game.handle_input(Input::MoveRightStart));
+        assert_eq!(shifted(&game), 1);
+        for _ in 0..9 {
+            game.tick();
+        }
+        assert_eq!(shifted(&game), 1);
+
+        game.tick();
+        assert_eq!(shifted(&game), 2);
+        game.tick();
+        assert_eq!(shifted(&game), 2);
+        game.tick();
+        assert_eq!(shifted(&game), 3);
+
+        game.handle_input(Input::MoveRightEnd);
+        for _ in 0..10 {
+            game.tick();
+        }
+        assert_eq!(shifted(&game), 3);
+    }
+
+    #[test]
+    fn zero_arr_shifts_straight_to_the_wall() {
+        let mut game = TetrisGame::with_settings(19, GameSettings { das: 5, arr: 0, ..Default::default() });
+
+        game.handle_input(Input::MoveLeftStart);
+        for _ in 0..5 {
+            game.tick();
+        }
+
+        let tetris = game.current_tetris();
+        assert_eq!(tetris.position.x + tetris.most_left().unwrap() as i32, 0);
+    }
+
+    #[test]
+    fn releases_stop_every_held_input() {
+        let mut game = TetrisGame::with_settings(19, GameSettings { das: 5, arr: 2, ..Default::default() });
+        game.handle_input(Input::MoveRightStart);
+        game.handle_input(Input::SoftDropStart);
+        for _ in 0..6 {
+            game.tick();
+        }
+
+        for input in Input::RELEASES {
+            game.handle_input(input);
+        }
+        let held = game.current_tetris().position;
+        for _ in 0..20 {
+            game.tick();
+        }
+
+        // Only gravity moves it, at most one row in a third of a second
+        let position = game.current_tetris().position;
+        assert_eq!(position.x, held.x);
+        assert!(position.y - held.y <= 1);
+    }
+
+    /// Row of the lowest square of the tetris
+    fn bottom_row(tetris: &Tetris) -> i32 {
+        tetris.position.y + tetris.most_bottom().unwrap() as i32
+    }
+
+    #[test]
+    fn every_tetris_spawns_one_row_under_the_buffer() {
+        let mut game = TetrisGame::new(23);
+        assert!(game.drain_events().is_empty());
+
+        for _ in 0..5 {
+            assert_eq!(bottom_row(game.current_tetris()), BUFFER_HEIGHT as i32);
+            game.hard_drop();
+        }
+    }
+
+    #[test]
+    fn blocked_tetris_spawns_in_the_buffer() {
+        let mut game = TetrisGame::new(23);
+        game.hard_drop();
+        let bottom = game.board.height() - 1;
+        for y in BUFFER_HEIGHT..=bottom {
+            for x in 1..game.board.width() {
+                game.board[y][x] = Some(TetrisType::Garbage);
+            }
+        }
+
+        game.hold_tetris();
+        assert_eq!(bottom_row(game.current_tetris()), BUFFER_HEIGHT as i32 - 1);
+        assert!(!game.is_gameover());
+
+        // Lock out: locking with every square in the buffer ends the game
+        game.drain_events();
+        tick_until_locked(&mut game);
+        assert!(game.is_gameover());
+        assert!(!game.goal_reached());
+    }
+
+    #[test]
+    fn spawning_on_the_stack_blocks_out() {
+        let mut game = TetrisGame::new(23);
+        let width = game.board.width();
+        for x in 1..width - 1 {
+            game.board[BUFFER_HEIGHT - 1][x] = Some(TetrisType::Garbage);
+            game.board[BUFFER_HEIGHT - 2][x] = Some(TetrisType::Garbage);
+        }
+
+        game.hold_tetris();
+
+        assert!(game.is_gameover());
+        assert!(game.drain_events().contains(&GameEvent::GameOver));
+    }
+}
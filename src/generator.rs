@@ -0,0 +1,182 @@
+use std::str::FromStr;
+
+use rand::Rng;
+use rand::rngs::SmallRng;
+
+use crate::tetris::{TetrisType, NATURAL_TETRIS};
+
+/// Decides which tetris comes next
+///
+/// Uses the game rng so that a seed always gives the same sequence.
+/// Network matches are set up on another thread, hence [`Send`]
+pub trait PieceGenerator: Send {
+    fn next_tetris(&mut self, rng: &mut SmallRng) -> TetrisType;
+}
+
+/// Generators shipped with the game, stored in the settings in place of the generator itself
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Randomizer {
+    /// Every tetris once per bag of 7
+    #[default]
+    Bag7,
+    /// Every tetris twice per bag of 14
+    Bag14,
+    /// Uniform random, allows long droughts
+    Random,
+    /// NES algorithm, rerolls once on repeat
+    Nes
+}
+
+impl Randomizer {
+    /// Every built-in generator, in menu order
+    pub const ALL: [Randomizer; 4] = [Randomizer::Bag7, Randomizer::Bag14, Randomizer::Random, Randomizer::Nes];
+
+    pub fn generator(&self) -> Box<dyn PieceGenerator> {
+        match self {
+            Randomizer::Bag7 => Box::new(BagGenerator::new(1)),
+            Randomizer::Bag14 => Box::new(BagGenerator::new(2)),
+            Randomizer::Random => Box::new(RandomGenerator),
+            Randomizer::Nes => Box::new(NesGenerator::default())
+        }
+    }
+}
+
+impl FromStr for Randomizer {
+    type Err = String;
+
+    /// Parses the names used on the command line: bag7, bag14, random and nes
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "bag7" | "7bag" => Ok(Randomizer::Bag7),
+            "bag14" | "14bag" => Ok(Randomizer::Bag14),
+            "random" => Ok(Randomizer::Random),
+            "nes" => Ok(Randomizer::Nes),
+            _ => Err(format!("unknown randomizer \"{}\", expected bag7, bag14, random or nes", name))
+        }
+    }
+}
+
+/// Shuffles every tetris of [`NATURAL_TETRIS`] `copies` times in a bag and deals them until empty
+pub struct BagGenerator {
+    copies: usize,
+    bag: Vec<TetrisType>
+}
+
+impl BagGenerator {
+    pub fn new(copies: usize) -> Self {
+        Self {
+            copies: copies.max(1),
+            bag: Vec::with_capacity(NATURAL_TETRIS.len() * copies.max(1))
+        }
+    }
+
+    fn refill(&mut self, rng: &mut SmallRng) {
+        for _ in 0..self.copies {
+            self.bag.extend_from_slice(&NATURAL_TETRIS);
+        }
+
+        // Fisher-Yates, done by hand so only the game rng is used
+        for index in (1..self.bag.len()).rev() {
+            self.bag.swap(index, rng.random_range(0..=index));
+        }
+    }
+}
+
+impl PieceGenerator for BagGenerator {
+    fn next_tetris(&mut self, rng: &mut SmallRng) -> TetrisType {
+        if self.bag.is_empty() {
+            self.refill(rng);
+        }
+
+        // Bag is never empty after a refill
+        self.bag.pop().unwrap()
+    }
+}
+
+/// Picks uniformly from [`NATURAL_TETRIS`]
+pub struct RandomGenerator;
+
+impl PieceGenerator for RandomGenerator {
+    fn next_tetris(&mut self, rng: &mut SmallRng) -> TetrisType {
+        NATURAL_TETRIS[rng.random_range(0..NATURAL_TETRIS.len())]
+    }
+}
+
+/// Classic NES randomizer
+///
+/// Rolls an 8 sided dice, if it lands on the extra side or on the previous tetris it rerolls once with 7 sides
+#[derive(Default)]
+pub struct NesGenerator {
+    previous: Option<TetrisType>
+}
+
+impl PieceGenerator for NesGenerator {
+    fn next_tetris(&mut self, rng: &mut SmallRng) -> TetrisType {
+        let roll = rng.random_range(0..=NATURAL_TETRIS.len());
+
+        let tetris = if roll == NATURAL_TETRIS.len() || Some(NATURAL_TETRIS[roll]) == self.previous {
+            NATURAL_TETRIS[rng.random_range(0..NATURAL_TETRIS.len())]
+        } else {
+            NATURAL_TETRIS[roll]
+        };
+
+        self.previous = Some(tetris);
+        tetris
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    /// First `count` tetris of the generator
+    fn deal(randomizer: Randomizer, seed: u64, count: usize) -> Vec<TetrisType> {
+        let mut generator = randomizer.generator();
+        let mut rng = SmallRng::seed_from_u64(seed);
+        (0..count).map(|_| generator.next_tetris(&mut rng)).collect()
+    }
+
+    fn occurrences(tetris: &[TetrisType], tetris_type: TetrisType) -> usize {
+        tetris.iter().filter(|dealt| **dealt == tetris_type).count()
+    }
+
+    #[test]
+    fn bag7_deals_every_tetris_once_per_bag() {
+        for seed in 0..20 {
+            for bag in deal(Randomizer::Bag7, seed, 7 * 50).chunks(7) {
+                for tetris_type in NATURAL_TETRIS {
+                    assert_eq!(occurrences(bag, tetris_type), 1, "seed {}, bag {:?}", seed, bag);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bag14_deals_every_tetris_twice_per_bag() {
+        for seed in 0..20 {
+            for bag in deal(Randomizer::Bag14, seed, 14 * 50).chunks(14) {
+                for tetris_type in NATURAL_TETRIS {
+                    assert_eq!(occurrences(bag, tetris_type), 2, "seed {}, bag {:?}", seed, bag);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_deals_the_same_tetris() {
+        for randomizer in Randomizer::ALL {
+            assert_eq!(deal(randomizer, 99, 100), deal(randomizer, 99, 100), "{:?}", randomizer);
+        }
+        assert_ne!(deal(Randomizer::Bag7, 1, 100), deal(Randomizer::Bag7, 2, 100));
+    }
+
+    #[test]
+    fn names_parse() {
+        assert_eq!("bag7".parse(), Ok(Randomizer::Bag7));
+        assert_eq!("14BAG".parse(), Ok(Randomizer::Bag14));
+        assert_eq!("nes".parse(), Ok(Randomizer::Nes));
+        assert!("bag8".parse::<Randomizer>().is_err());
+    }
+}
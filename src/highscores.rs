@@ -0,0 +1,410 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tetris_sdl_rs::hash::fnv1a;
+use tetris_sdl_rs::{GameMode, GameSettings, GameStats};
+
+use crate::dirs::data_dir;
+
+/// Entries kept per mode
+pub const ENTRIES_PER_MODE: usize = 10;
+/// Longest name the player can enter
+pub const MAX_NAME_LENGTH: usize = 12;
+
+/// First line of the file, bumped when the format changes
+const FILE_HEADER: &str = "tetris-highscores 1";
+const FILE_NAME: &str = "highscores.txt";
+
+/// One finished game in the table
+#[derive(Clone, Debug, PartialEq)]
+pub struct HighScore {
+    pub mode: GameMode,
+    pub name: String,
+    /// Seconds since the unix epoch
+    pub date: u64,
+    pub stats: GameStats
+}
+
+impl HighScore {
+    /// Entry for a game that just ended
+    pub fn new(mode: GameMode, name: &str, stats: GameStats) -> Self {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            mode,
+            name: clean_name(name),
+            date,
+            stats
+        }
+    }
+
+    /// Date as year-month-day, in UTC
+    pub fn date_text(&self) -> String {
+        // Days to civil date, from Howard Hinnant's date algorithms
+        let days = (self.date / 86400) as i64 + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        format!("{}-{:02}-{:02}", year, month, day)
+    }
+
+    /// True if it is better than `other`. Sprint ranks by time, the other modes by score
+    fn beats(&self, other: &HighScore) -> bool {
+        if self.mode == GameMode::Sprint {
+            self.stats.ticks < other.stats.ticks
+        } else {
+            self.stats.score > other.stats.score
+        }
+    }
+
+    /// Only games on the default board started at level 1 count, entries don't remember the settings.
+    /// Sprint games also need the 40 lines cleared
+    fn counts(settings: &GameSettings, stats: &GameStats) -> bool {
+        let standard = GameSettings::default();
+        settings.width == standard.width
+            && settings.height == standard.height
+            && settings.start_level == standard.start_level
+            && (settings.mode != GameMode::Sprint || stats.goal_reached)
+    }
+
+    fn to_line(&self) -> String {
+        let stats = &self.stats;
+        format!(
+            "{:?}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.mode, self.name, self.date, stats.seed, stats.score, stats.lines,
+            stats.level, stats.pieces, stats.ticks, stats.goal_reached as u8
+        )
+    }
+
+    fn from_line(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [mode, name, date, seed, score, lines, level, pieces, ticks, goal_reached] = fields[..] else {
+            return Err(format!("expected 10 fields, found {}", fields.len()));
+        };
+
+        let number = |field: &str| field.parse::<u64>().map_err(|e| format!("\"{}\": {}", field, e));
+        Ok(Self {
+            mode: GameMode::ALL
+                .into_iter()
+                .find(|value| format!("{:?}", value) == mode)
+                .ok_or(format!("unknown mode \"{}\"", mode))?,
+            name: clean_name(name),
+            date: number(date)?,
+            stats: GameStats {
+                seed: number(seed)?,
+                score: number(score)? as usize,
+                lines: number(lines)? as usize,
+                level: number(level)? as usize,
+                pieces: number(pieces)? as usize,
+                ticks: number(ticks)?,
+                goal_reached: number(goal_reached)? != 0
+            }
+        })
+    }
+}
+
+/// Best games of each mode, saved in the user data directory
+pub struct HighScores {
+    entries: Vec<HighScore>,
+    /// None when no data directory could be found, the table then only lives in memory
+    path: Option<PathBuf>,
+    /// Set when the file couldn't be read or written, shown on the high scores screen
+    pub error: Option<String>
+}
+
+impl HighScores {
+    /// Reads the table from `$XDG_DATA_HOME/tetris-sdl-rs`, or `~/.local/share/tetris-sdl-rs`
+    ///
+    /// A corrupted file is reported in `error` and renamed so it isn't overwritten. When it can't be read or renamed,
+    /// the table only lives in memory
+    pub fn load() -> Self {
+        Self::load_from(data_dir().map(|dir| dir.join(FILE_NAME)))
+    }
+
+    /// [`HighScores::load`] from the given file, None keeps the table in memory
+    fn load_from(path: Option<PathBuf>) -> Self {
+        let mut high_scores = Self {
+            entries: Vec::new(),
+            path,
+            error: None
+        };
+
+        let Some(path) = high_scores.path.clone() else {
+            high_scores.error = Some("no data directory, high scores won't be saved".to_string());
+            return high_scores;
+        };
+
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return high_scores,
+            Err(e) => {
+                // Saving would replace a file we never read
+                high_scores.path = None;
+                high_scores.error = Some(format!("can't read {}: {}, high scores won't be saved", path.display(), e));
+                eprintln!("{}", e);
+                return high_scores;
+            }
+        };
+
+        match parse(&text) {
+            Ok(entries) => high_scores.entries = entries,
+            Err(e) => {
+                let backup = path.with_extension("txt.corrupt");
+                high_scores.error = Some(match std::fs::rename(&path, &backup) {
+                    Ok(()) => format!("high scores were corrupted ({}), moved to {}", e, backup.display()),
+                    Err(rename_error) => {
+                        high_scores.path = None;
+                        format!("high scores were corrupted ({}) and can't be moved aside ({}), they won't be saved", e, rename_error)
+                    }
+                });
+            }
+        }
+
+        if let Some(error) = &high_scores.error {
+            eprintln!("{}", error);
+        }
+        high_scores
+    }
+
+    /// Entries of a mode from best to worst
+    pub fn ranked(&self, mode: GameMode) -> Vec<&HighScore> {
+        let mut entries: Vec<&HighScore> = self.entries.iter().filter(|entry| entry.mode == mode).collect();
+        entries.sort_by(|a, b| {
+            if a.beats(b) {
+                std::cmp::Ordering::Less
+            } else if b.beats(a) {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        });
+        entries
+    }
+
+    /// True if the game played with these settings would make it in the table
+    pub fn qualifies(&self, settings: &GameSettings, stats: &GameStats) -> bool {
+        if !HighScore::counts(settings, stats) {
+            return false;
+        }
+
+        let ranked = self.ranked(settings.mode);
+        let candidate = HighScore::new(settings.mode, "", *stats);
+        ranked.len() < ENTRIES_PER_MODE || ranked.last().is_some_and(|last| candidate.beats(last))
+    }
+
+    /// Adds the entry, drops the ones pushed out of the top and saves the file
+    pub fn add(&mut self, entry: HighScore) {
+        let mode = entry.mode;
+        self.entries.push(entry);
+
+        let kept: Vec<HighScore> = self.ranked(mode).into_iter().take(ENTRIES_PER_MODE).cloned().collect();
+        self.entries.retain(|entry| entry.mode != mode);
+        self.entries.extend(kept);
+
+        if let Err(e) = self.save() {
+            eprintln!("{}", e);
+            self.error = Some(e);
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("can't create {}: {}", dir.display(), e))?;
+        }
+
+        let mut text = format!("{}\n", FILE_HEADER);
+        for entry in &self.entries {
+            text.push_str(&entry.to_line());
+            text.push('\n');
+        }
+        text.push_str(&format!("checksum {:016x}\n", checksum(&text)));
+
+        std::fs::write(path, text).map_err(|e| format!("can't write {}: {}", path.display(), e))
+    }
+}
+
+/// Reads every entry, checking the header and the checksum
+fn parse(text: &str) -> Result<Vec<HighScore>, String> {
+    let body_end = text.trim_end().rfind('\n').map(|index| index + 1).ok_or("missing checksum")?;
+    let (body, checksum_line) = text.split_at(body_end);
+
+    let expected = checksum_line
+        .trim()
+        .strip_prefix("checksum ")
+        .and_then(|value| u64::from_str_radix(value, 16).ok())
+        .ok_or("missing checksum")?;
+    if checksum(body) != expected {
+        return Err("checksum mismatch".to_string());
+    }
+
+    let mut lines = body.lines();
+    if lines.next() != Some(FILE_HEADER) {
+        return Err("unknown header".to_string());
+    }
+
+    lines
+        .enumerate()
+        .map(|(index, line)| HighScore::from_line(line).map_err(|e| format!("entry {}: {}", index + 1, e)))
+        .collect()
+}
+
+/// Enough to notice a damaged or hand edited file
+fn checksum(text: &str) -> u64 {
+    fnv1a(text.as_bytes())
+}
+
+/// Names are stored between tabs on a single line
+fn clean_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LENGTH)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(mode: GameMode, name: &str, score: usize, ticks: u64) -> HighScore {
+        HighScore {
+            mode,
+            name: name.to_string(),
+            date: 1_700_000_000,
+            stats: GameStats { seed: 5, score, lines: 40, level: 4, pieces: 100, ticks, goal_reached: true }
+        }
+    }
+
+    /// Empty directory for a test, removed first if an old run left it
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tetris-highscores-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn saved_table_loads_back() {
+        let dir = test_dir("round-trip");
+        let path = dir.join(FILE_NAME);
+
+        let mut high_scores = HighScores::load_from(Some(path.clone()));
+        assert_eq!(high_scores.error, None);
+        high_scores.add(entry(GameMode::Marathon, "Ann", 5000, 9000));
+        high_scores.add(entry(GameMode::Sprint, "Bob", 100, 3000));
+
+        let loaded = HighScores::load_from(Some(path));
+        assert_eq!(loaded.error, None);
+        assert_eq!(loaded.entries, high_scores.entries);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn checksum_catches_edits() {
+        let mut text = format!("{}\n{}\n", FILE_HEADER, entry(GameMode::Ultra, "Ann", 5000, 7200).to_line());
+        text.push_str(&format!("checksum {:016x}\n", checksum(&text)));
+        assert_eq!(parse(&text).map(|entries| entries.len()), Ok(1));
+
+        let edited = text.replace("5000", "9000");
+        assert_eq!(parse(&edited), Err("checksum mismatch".to_string()));
+        assert!(parse(text.trim_end().rsplit_once('\n').unwrap().0).is_err());
+        assert!(parse("").is_err());
+
+        let mut other_header = text.replace(FILE_HEADER, "tetris-highscores 0");
+        other_header.truncate(other_header.find("checksum").unwrap());
+        let checksum_line = format!("checksum {:016x}\n", checksum(&other_header));
+        assert_eq!(parse(&(other_header + &checksum_line)), Err("unknown header".to_string()));
+    }
+
+    #[test]
+    fn corrupted_file_is_moved_aside() {
+        let dir = test_dir("corrupted");
+        let path = dir.join(FILE_NAME);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "tetris-highscores 1\nnot a score\nchecksum 0000000000000000\n").unwrap();
+
+        let high_scores = HighScores::load_from(Some(path.clone()));
+
+        assert!(high_scores.error.as_ref().is_some_and(|error| error.contains("corrupted")));
+        assert!(high_scores.entries.is_empty());
+        assert!(!path.exists());
+        assert!(path.with_extension("txt.corrupt").exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn unreadable_file_is_never_overwritten() {
+        let dir = test_dir("unreadable");
+        let path = dir.join(FILE_NAME);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, [0xff, 0xfe, 0x00]).unwrap();
+
+        let mut high_scores = HighScores::load_from(Some(path.clone()));
+        assert!(high_scores.error.as_ref().is_some_and(|error| error.contains("won't be saved")));
+        high_scores.add(entry(GameMode::Marathon, "Ann", 100, 0));
+
+        assert_eq!(high_scores.ranked(GameMode::Marathon).len(), 1);
+        assert_eq!(std::fs::read(&path).unwrap(), [0xff, 0xfe, 0x00]);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn keeps_the_best_10_per_mode() {
+        let mut high_scores = HighScores::load_from(None);
+        for score in 0..15 {
+            high_scores.add(entry(GameMode::Marathon, "Ann", score * 100, 0));
+            high_scores.add(entry(GameMode::Sprint, "Bob", 0, 10_000 - score as u64 * 100));
+        }
+
+        let marathon = high_scores.ranked(GameMode::Marathon);
+        assert_eq!(marathon.len(), ENTRIES_PER_MODE);
+        assert_eq!(marathon[0].stats.score, 1400);
+        assert_eq!(marathon[9].stats.score, 500);
+
+        // Sprint ranks by time
+        let sprint = high_scores.ranked(GameMode::Sprint);
+        assert_eq!(sprint[0].stats.ticks, 8600);
+        let sprint = GameSettings { mode: GameMode::Sprint, ..Default::default() };
+        assert!(high_scores.qualifies(&sprint, &entry(GameMode::Sprint, "", 0, 8000).stats));
+        assert!(!high_scores.qualifies(&sprint, &entry(GameMode::Sprint, "", 0, 9999).stats));
+
+        let mut unfinished = entry(GameMode::Sprint, "", 0, 10).stats;
+        unfinished.goal_reached = false;
+        assert!(!high_scores.qualifies(&sprint, &unfinished));
+    }
+
+    #[test]
+    fn only_standard_games_qualify() {
+        let high_scores = HighScores::load_from(None);
+        let stats = entry(GameMode::Marathon, "", 100, 0).stats;
+        assert!(high_scores.qualifies(&GameSettings::default(), &stats));
+
+        for settings in [
+            GameSettings { width: 6, ..Default::default() },
+            GameSettings { height: 30, ..Default::default() },
+            GameSettings { start_level: 10, ..Default::default() }
+        ] {
+            assert!(!high_scores.qualifies(&settings, &stats));
+        }
+    }
+
+    #[test]
+    fn names_fit_on_one_line() {
+        assert_eq!(clean_name("  Ann\t\n  "), "Ann");
+        assert_eq!(clean_name("AnnaMariaJosephine").chars().count(), MAX_NAME_LENGTH);
+        assert_eq!(entry(GameMode::Marathon, "", 0, 0).date_text(), "2023-11-14");
+    }
+}
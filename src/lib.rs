@@ -0,0 +1,52 @@
+//! Tetris game engine
+//!
+//! The engine does not draw anything and does not depend on SDL, it only holds the game state.
+//! Drive it by feeding [`Input`]s and calling [`TetrisGame::tick`] once per frame,
+//! then read the state back with the getters or [`TetrisGame::drain_events`].
+//!
+//! ```
+//! use tetris_sdl_rs::{Input, TetrisGame};
+//!
+//! let mut game = TetrisGame::new(1221351235);
+//! game.handle_input(Input::MoveLeft);
+//!
+//! while !game.is_gameover() {
+//!     game.tick();
+//! }
+//!
+//! println!("score: {}, lines cleared: {}", game.score(), game.lines());
+//! ```
+
+pub mod ai;
+// args and hash only share code with the binaries, they aren't part of the game API
+#[doc(hidden)]
+pub mod args;
+pub mod board;
+pub mod env;
+pub mod game;
+pub mod generator;
+#[doc(hidden)]
+pub mod hash;
+pub mod netplay;
+pub mod replay;
+pub mod rotation;
+pub mod scoring;
+pub mod spectate;
+pub mod tetris;
+pub mod versus;
+
+pub use ai::{Ai, AiWeights, AI_INPUT_DELAY};
+pub use board::{Board, BUFFER_HEIGHT, MAX_BOARD_HEIGHT, MAX_BOARD_WIDTH, MIN_BOARD_HEIGHT, MIN_BOARD_WIDTH};
+pub use env::{Action, EnvConfig, Observation, RewardShaping, StepInfo, TetrisEnv};
+pub use game::{
+    GameEvent, GameMode, GameSettings, GameStats, Input, LockReset, TetrisGame,
+    GAME_HEIGHT, GAME_WIDTH, MAX_ARR, MAX_DAS, MAX_LOCK_DELAY, MAX_QUEUE_LENGTH, MAX_SOFT_DROP_FACTOR, SPRINT_LINES,
+    TICKS_PER_SECOND, ULTRA_TICKS
+};
+pub use generator::{PieceGenerator, Randomizer};
+pub use netplay::{NetRole, NetSession};
+pub use replay::{Replay, ReplayPlayer};
+pub use rotation::{Rotation, RotationSystem, RotationSystemKind};
+pub use spectate::Broadcaster;
+pub use tetris::{Position, Tetris, TetrisMap, TetrisType, NATURAL_TETRIS};
+pub use versus::{Versus, VersusResult, PLAYERS};
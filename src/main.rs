@@ -1,38 +1,50 @@
-use rand::rngs::SmallRng;
-use rand::{Rng, RngCore, SeedableRng};
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-use sdl2::controller::Button;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
+mod cli;
+mod controls;
+mod dirs;
+mod highscores;
+mod menu;
+mod pads;
+mod renderer;
+mod screens;
+
+use sdl2::event::{Event, WindowEvent};
+use std::ops::ControlFlow;
 use std::time::Duration;
 
-const GAME_WIDTH: usize = 10;
-const GAME_HEIGHT: usize = 20;
-const GAME_RATIO: usize = 50;
-const FPS: usize = 60;
-
-const NATURAL_TETRIS: [TetrisType; 7] = [
-            TetrisType::Line,
-            TetrisType::LShapeRight,
-            TetrisType::LShapeLeft,
-            TetrisType::ZShapeRight,
-            TetrisType::ZShapeLeft,
-            TetrisType::TShape,
-            TetrisType::Square,
-];
+use controls::{Action, Controls};
+use highscores::{HighScore, HighScores};
+use menu::{Menu, MenuAction};
+use renderer::{format_time, Renderer, SIDE_PANEL_WIDTH};
+use tetris_sdl_rs::{Ai, Broadcaster, GameMode, GameSettings, GameStats, Input, Replay, TetrisGame, GAME_HEIGHT, PLAYERS, TICKS_PER_SECOND};
+
+const FPS: usize = TICKS_PER_SECOND;
+const FONT_PATH: &str = "src/Roboto.ttf";
+
+const PAUSE_ITEMS: [&str; 4] = ["Resume", "Restart", "Settings", "Quit to title"];
 
 fn main() -> Result<(), String> {
+    let options = match cli::parse()? {
+        Some(options) => options,
+        None => return Ok(())
+    };
+    let scale = options.scale;
+    let replay = options.replay.as_ref().map(Replay::load).transpose()?;
+
+    // The board of a replay has the size it was recorded with
+    let board_settings = replay.as_ref().map(|replay| replay.settings).unwrap_or(options.settings);
+    // Short boards still need room for the side panel
+    let window_width = scale * (board_settings.width + SIDE_PANEL_WIDTH);
+    // Network matches show both boards from the start
+    let boards = if options.net.is_some() { PLAYERS } else { 1 };
+    let window_height = scale * board_settings.height.max(GAME_HEIGHT);
+
     // Initialize SDL
     let sdl_context = sdl2::init()?;
-    let game_controller_subsystem = sdl_context.game_controller()?;
     let video_subsystem = sdl_context.video()?;
 
     // Create a window
     let window = video_subsystem
-        .window("Tetris", (GAME_RATIO * GAME_WIDTH) as u32, (GAME_RATIO * GAME_HEIGHT) as u32)
+        .window("Tetris", (window_width * boards) as u32, window_height as u32)
         .position_centered()
         .build()
         .map_err(|e| e.to_string())?;
@@ -46,139 +58,250 @@ fn main() -> Result<(), String> {
         .build()
         .map_err(|e| e.to_string())?;
 
-    // Set up event handling
-    let mut event_pump = sdl_context.event_pump()?;
-
-    let available = game_controller_subsystem
-        .num_joysticks()
-        .map_err(|e| format!("can't enumerate joysticks: {}", e))?;
-
-
-    let _controller = (0..available)
-        .find_map(|id| {
-            if !game_controller_subsystem.is_game_controller(id) {
-                println!("{} is not a game controller", id);
-                return None;
-            }
+    // Load the font once for every screens
+    let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let font = ttf_context.load_font(FONT_PATH, 24)?;
 
-            println!("Attempting to open controller {}", id);
-
-            match game_controller_subsystem.open(id) {
-                Ok(c) => {
-                    // We managed to find and open a game controller,
-                    // exit the loop
-                    println!("Success: opened \"{}\"", c.name());
-                    Some(c)
-                }
-                Err(e) => {
-                    println!("failed: {:?}", e);
-                    None
-                }
-            }
-        });
+    // Set up event handling, controllers can be plugged in and out at any time
+    let mut event_pump = pads::EventPump::new(&sdl_context)?;
 
     //todo: change this so that state can be change by multiple threads
-    let mut current_state = STATE::Tetris;
-    
+    let mut current_state = State::Title;
+    // Used by every new game, starts from the command line and is changed in the settings screen
+    let mut settings = options.settings;
+    // Best games of each mode, kept between runs
+    let mut high_scores = HighScores::load();
+    // Last name entered, offered again for the next high score
+    let mut player_name = String::from("Player");
+    // Keys and buttons of the game, changed in the controls screen
+    let mut controls = Controls::load();
+    // Viewers of the games, when asked for on the command line
+    let mut broadcaster = options.spectate.as_deref().map(Broadcaster::bind).transpose()?;
+
+    let mut renderer = Renderer::new(&mut canvas, &font, scale as u32);
+
+    if let Some(replay) = &replay {
+        return screens::replay(&mut renderer, &mut event_pump, replay);
+    }
+    if let Some(role) = &options.net {
+        return screens::net_versus(&mut renderer, &mut event_pump, &mut controls, &mut broadcaster, role, options.next_seed(), settings);
+    }
+
     // Main game loop
     'running: loop {
-        // todo: add menu state
         match current_state {
-            STATE::Gameover(score) => {
-                // Redraw background
-                canvas.set_draw_color(Color::RGB(0, 0, 0));
-                canvas.clear();
-
-                let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
-
-                let font_path = "src/Roboto.ttf";
-            
-                let font = ttf_context.load_font(font_path, 24)?;
-                let surface = font
-                .render(&format!("Your score is: {}", score))
-                .blended(Color::RGB(255, 255, 255))
-                .map_err(|e| e.to_string())?;
-            
-                let texture_creator = canvas.texture_creator();
-                let texture = texture_creator
-                    .create_texture_from_surface(&surface)
-                    .map_err(|e| e.to_string())?;
-            
-                let target = Rect::new(
-                    0,
-                    (GAME_HEIGHT * GAME_RATIO) as i32 / 2,
-                (GAME_WIDTH * GAME_RATIO) as u32,
-                40,
-                );
+            State::Title => match screens::title(&mut renderer, &mut event_pump)? {
+                ControlFlow::Continue(next_state) => current_state = next_state,
+                ControlFlow::Break(()) => break 'running
+            },
+            State::ModeSelect => match screens::mode_select(&mut renderer, &mut event_pump)? {
+                ControlFlow::Continue(next_state) => current_state = next_state,
+                ControlFlow::Break(()) => break 'running
+            },
+            State::Settings => {
+                if screens::settings(&mut renderer, &mut event_pump, &mut settings)?.is_break() {
+                    break 'running;
+                }
+                current_state = State::Title;
+            },
+            State::Controls => {
+                if screens::controls(&mut renderer, &mut event_pump, &mut controls)?.is_break() {
+                    break 'running;
+                }
+                current_state = State::Title;
+            },
+            State::HighScores => {
+                if screens::high_scores(&mut renderer, &mut event_pump, &high_scores)?.is_break() {
+                    break 'running;
+                }
+                current_state = State::Title;
+            },
+            State::Credits => {
+                if screens::credits(&mut renderer, &mut event_pump)?.is_break() {
+                    break 'running;
+                }
+                current_state = State::Title;
+            },
+            State::Gameover(game_settings, stats) => {
+                let mode = game_settings.mode;
+                let title = if stats.goal_reached { "FINISHED" } else { "GAME OVER" };
+                let mut lines = vec![
+                    format!("Your score is: {}", stats.score),
+                    format!("Lines: {}", stats.lines),
+                    format!("Time: {}", format_time(stats.ticks)),
+                    format!("Seed: {}", stats.seed)
+                ];
+
+                // The player didn't play the AI's games
+                if !options.ai && high_scores.qualifies(&game_settings, &stats) {
+                    match screens::name_entry(&mut renderer, &mut event_pump, title, &lines, &mut player_name)? {
+                        ControlFlow::Break(()) => break 'running,
+                        ControlFlow::Continue(true) => {
+                            if player_name.trim().is_empty() {
+                                player_name = String::from("Player");
+                            }
+                            high_scores.add(HighScore::new(mode, &player_name, stats));
+                        },
+                        ControlFlow::Continue(false) => {}
+                    }
+
+                    current_state = State::Title;
+                    continue;
+                }
+
+                lines.extend([String::new(), "Press any key".to_string()]);
+                renderer.draw_page(title, &lines, None)?;
 
                 'gameover: loop {
-                    // Now we can safely use canvas since current_game is None
                     for event in event_pump.poll_iter() {
                         match event {
-                            Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                            Event::Quit { .. } => {
                                 break 'running;
                             },
-                            Event::KeyDown { keycode: Some(_), .. } | Event::ControllerButtonUp { .. } => {
+                            Event::KeyDown { keycode: Some(_), repeat: false, .. } | Event::ControllerButtonUp { .. } => {
                                 break 'gameover; // Exit state
                             },
                             _ => {}
                         }
                     }
 
-                    canvas.copy(&texture, None, Some(target))?;
-                    canvas.present();
+                    std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));
+                }
+
+                current_state = State::Title;
+            },
+            State::Versus => {
+                // Room for both boards and their side panels
+                renderer.canvas.window_mut()
+                    .set_size((window_width * PLAYERS) as u32, window_height as u32)
+                    .map_err(|e| e.to_string())?;
 
-                    std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));   
+                match screens::versus(&mut renderer, &mut event_pump, &controls, &mut broadcaster, options.next_seed(), settings)? {
+                    ControlFlow::Continue(next_state) => current_state = next_state,
+                    ControlFlow::Break(()) => break 'running
                 }
 
-                current_state = STATE::Tetris;
+                if !matches!(current_state, State::Versus) {
+                    renderer.canvas.window_mut()
+                        .set_size(window_width as u32, window_height as u32)
+                        .map_err(|e| e.to_string())?;
+                }
             },
-            STATE::Tetris => {
-                // Redraw background
-                canvas.set_draw_color(Color::RGB(0, 0, 0));
-                canvas.clear();
+            State::Tetris(mode) => {
+                let game_settings = GameSettings { mode, ..settings };
+                let mut game = TetrisGame::with_settings(options.next_seed(), game_settings);
+                let mut replay = Replay::new(game.seed(), game_settings);
+                // Some when the AI plays, the player can still pause
+                let mut ai = options.ai.then(Ai::default);
+                renderer.draw(&game)?;
 
-                let mut game = TetrisGame::new(&mut canvas, 1221351235);
+                // Some while paused, the game doesn't tick
+                let mut pause_menu: Option<Menu> = None;
 
-                // todo: add a substate for pausing the game
                 loop {
-                    for event in event_pump.poll_iter() {
-                        match event {
-                            Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                                break 'running;
+                    if let Some(menu) = pause_menu.as_mut() {
+                        let mut action = None;
+                        for event in event_pump.poll_iter() {
+                            match event {
+                                _ if controls.changes(&event).contains(&(Action::Pause, true)) => action = Some(MenuAction::Back),
+                                _ => action = menu.handle_event(&event).or(action)
+                            }
+                        }
+
+                        match action {
+                            Some(MenuAction::Quit) => break 'running,
+                            Some(MenuAction::Back) | Some(MenuAction::Select(0)) => {
+                                pause_menu = None;
+                                renderer.draw(&game)?;
                             },
-                            Event::KeyDown { keycode: Some(Keycode::Left), .. } | Event::ControllerButtonDown { button: Button::DPadLeft, .. } => {
-                                game.move_tetris_with_check(Position { x: -1, y: 0 })?;
-                                game.canvas.present();
+                            Some(MenuAction::Select(1)) => {
+                                game = TetrisGame::with_settings(options.next_seed(), GameSettings { mode, ..settings });
+                                replay = Replay::new(game.seed(), *game.settings());
+                                ai = options.ai.then(Ai::default);
+                                pause_menu = None;
+                                renderer.draw(&game)?;
                             },
-                            Event::KeyDown { keycode: Some(Keycode::Right), .. } | Event::ControllerButtonDown { button: Button::DPadRight, .. } => {
-                                game.move_tetris_with_check(Position { x: 1, y: 0 })?;
-                                game.canvas.present();
+                            Some(MenuAction::Select(2)) => {
+                                let flow = screens::settings(&mut renderer, &mut event_pump, &mut settings)?;
+                                if flow.is_break() {
+                                    break 'running;
+                                }
                             },
-                            Event::KeyDown { keycode: Some(Keycode::X), .. } | Event::ControllerButtonDown { button: Button::A, .. } => {
-                                game.rotate_tetris_left()?;
-                                game.canvas.present();
+                            Some(MenuAction::Select(3)) => {
+                                current_state = State::Title;
+                                break;
                             },
-                            Event::KeyDown { keycode: Some(Keycode::C), .. } | Event::ControllerButtonDown { button: Button::B, .. } => {
-                                game.rotate_tetris_right()?;
-                                game.canvas.present();
+                            _ => {}
+                        }
+
+                        if let Some(menu) = &pause_menu {
+                            renderer.draw_menu("PAUSED", menu)?;
+                        }
+
+                        std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));
+                        continue;
+                    }
+
+                    // Inputs are given after polling so they can be recorded with their tick
+                    let mut inputs = Vec::new();
+                    for event in event_pump.poll_iter() {
+                        match event {
+                            Event::Quit { .. } => {
+                                break 'running;
                             },
-                            Event::KeyDown { keycode: Some(Keycode::Down), .. } | Event::ControllerButtonDown { button: Button::DPadDown, .. } => {
-                                game.fast_falling = true;
+                            Event::Window { win_event: WindowEvent::FocusLost, .. } => {
+                                inputs.extend(Input::RELEASES);
+                                pause_menu = Some(Menu::new(&PAUSE_ITEMS));
                             },
-                            Event::KeyUp { keycode: Some(Keycode::Down), .. } | Event::ControllerButtonUp { button: Button::DPadDown, .. } => {
-                                game.fast_falling = false;
+                            Event::ControllerDeviceRemoved { which, .. } => {
+                                // The player may still be holding something on the pad that is gone
+                                controls.forget_pad(which);
+                                inputs.extend(Input::RELEASES);
+                                pause_menu = Some(Menu::new(&PAUSE_ITEMS));
                             },
-                            _ => {}
+                            _ => {
+                                // Auto repeat is done by the game, OS key repeats are ignored by the controls
+                                for change in controls.changes(&event) {
+                                    match change {
+                                        (Action::Pause, true) => {
+                                            inputs.extend(Input::RELEASES);
+                                            pause_menu = Some(Menu::new(&PAUSE_ITEMS));
+                                        },
+                                        (action, pressed) => inputs.extend(action.input(pressed))
+                                    }
+                                }
+                            }
                         }
                     }
 
+                    if let Some(ai) = ai.as_mut() {
+                        inputs.clear();
+                        inputs.extend(ai.paced_input(&game));
+                    }
+
+                    for input in inputs {
+                        replay.record(game.ticks(), input);
+                        game.handle_input(input);
+                    }
+
                     // Run game
-                    game.update_timer()?;
+                    game.tick();
+                    if let Some(broadcaster) = &mut broadcaster {
+                        broadcaster.broadcast(0, &game);
+                    }
+
+                    // Only redraw when something changed, timed modes need their clock to move
+                    if !game.drain_events().is_empty() || mode != GameMode::Marathon {
+                        renderer.draw(&game)?;
+                    }
 
                     // Exit if gameover
-                    if game.gameover {
-                        current_state = STATE::Gameover(game.score);
+                    if game.is_gameover() {
+                        match replay.save(&options.record) {
+                            Ok(()) => println!("Replay saved to {}", options.record.display()),
+                            Err(e) => println!("{}", e)
+                        }
+                        current_state = State::Gameover(*game.settings(), game.stats());
                         break;
                     }
 
@@ -191,527 +314,17 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
-enum STATE {
-    Tetris,
-    /// Stores the score
-    Gameover(usize)
+enum State {
+    Title,
+    ModeSelect,
+    Settings,
+    Controls,
+    HighScores,
+    Credits,
+    /// Playing the given mode
+    Tetris(GameMode),
+    /// Two players side by side
+    Versus,
+    /// Stores how the game went and the settings it was played with
+    Gameover(GameSettings, GameStats)
 }
-
-/// Basic position struct for position handeling
-#[derive(Clone, Copy)]
-struct Position {
-    x: i32,
-    y: i32
-}
-
-/// This struct contains all game logic and game states
-struct TetrisGame<'a> {
-    /// surface to draw on
-    canvas: &'a mut Canvas<Window>,
-    /// number of lines cleared
-    score: usize,
-    /// game grid. Uses GAME_WIDTH and GAME_HEIGHT for size
-    /// 
-    /// Only contains the placed squares with thier colors. Not the tetris themselves
-    board: [[Option<TetrisType>; GAME_WIDTH]; GAME_HEIGHT],
-    /// Number of pixel each board squares uses
-    size_ratio: u32, // Size in pixel of each square
-    /// rng generator
-    rng: SmallRng,
-    /// Acelerates when player is pressing down
-    fast_falling: bool,
-    /// Number of times counter needs to increment so that a game update happens
-    refresh_speed: usize,
-    /// Counter for time passing
-    refresh_counter: usize,
-    /// Contains the struct of the current falling tetris
-    current_tetris: Tetris,
-    /// When true main loop will exit game
-    gameover: bool
-}
-
-impl<'a> TetrisGame<'a> {
-    /// Creates a new TetrisGame object
-    fn new(canvas: &'a mut Canvas<Window>, seed: u64) -> Self {
-        Self {
-            canvas,
-            score: 0,
-            board: [[None; GAME_WIDTH]; GAME_HEIGHT],
-            size_ratio: GAME_RATIO as u32,
-            rng: rand::rngs::SmallRng::seed_from_u64(seed),
-            fast_falling: false,
-            refresh_speed: 20,
-            refresh_counter: 0,
-            current_tetris: Tetris::new(TetrisType::LShapeLeft, Color { r: 255, g: 0, b: 0, a: 0 }),
-            gameover: false
-        }
-    }
-
-    /// Creates a new current tetris with rng
-    fn make_new_random_tetris(&mut self) {
-        
-
-        let tetris = &NATURAL_TETRIS[self.rng.random_range(0..NATURAL_TETRIS.len())];
-
-        self.current_tetris = Tetris::new(tetris.clone(), self.get_tetris_color(tetris));
-    }
-
-    /// Get the color related to each tetris based on the score
-    /// All custom tetris are white by default
-    fn get_tetris_color(&self, tetris_type: &TetrisType) -> Color {
-        // Buffer that contains the RGB of each tetris
-        let mut color_buff = [0; NATURAL_TETRIS.len() * 3];
-
-        // Fill buff with colors for every types
-        // Always the same for every games
-        rand::rngs::SmallRng::seed_from_u64(self.score as u64)
-            .fill_bytes(&mut color_buff);
-
-        // Find the index of the tetris based on NATURAL_TETRIS
-        let tetris_index = NATURAL_TETRIS
-            .iter()
-            .enumerate()
-            .find_map(
-            |(index, x)| if x == tetris_type {Some(index)} else {None});
-        
-        // A bit hacky but does the job
-        if let Some(index) = tetris_index {
-            Color::RGB(color_buff[index * 3], color_buff[(index * 3) + 1], color_buff[(index * 3) + 2])
-        } else {
-            Color::RGB(255, 255, 255)
-        }
-    }
-
-    /// Function managing game speed and fastfall
-    /// 
-    /// Current behavior is that the games speeds up at each 2 lines clears
-    fn update_timer(&mut self) -> Result<(), String> {
-        let refresh_speed = {
-            if self.fast_falling {
-                ((self.refresh_speed - (self.score / 4)) as f32 * 0.3) as usize
-            } else {
-                self.refresh_speed - (self.score / 4)
-            }
-        }; // speedup based on lines cleared!self.check_tetris_hit_wall(&self.current_tetris, shift.x)
-
-        self.refresh_counter += 1;
-        if refresh_speed <= self.refresh_counter {
-            self.update()?;
-            self.refresh_counter = 0;
-        }
-        Ok(())
-    }
-
-    fn update(&mut self) -> Result<(), String> {
-
-        // If at bottom insert self in game map
-        if self.check_tetris_hit_bottom(&self.current_tetris, 1) || self.check_tetris_hit_board(&self.current_tetris, Position { x: 0, y: 1 }) {
-            let mut cleared_line = false;
-            self.insert_tetris_in_map();
-            self.make_new_random_tetris();
-
-            for (index, full) in self.full_lines().into_iter().enumerate() {
-                if full {
-                    self.clear_line(index);
-                    cleared_line = true;
-                    self.score += 1;
-                }
-            }
-
-            if cleared_line {
-                self.draw_refresh_all()?;
-            }
-            return Ok(())
-        }
-
-        self.move_tetris_with_check(Position { x: 0, y: 1 })?;
-        self.canvas.present();
-        Ok(())
-    }
-
-    /// Main draw function for the game grid
-    /// 
-    /// Draws a single square based on the position and uses ratio for each square size
-    fn draw_in_grid(&mut self, postion: Position) -> Result<(), String> {
-        let rectangle = Rect::new(
-            postion.x * self.size_ratio as i32,
-            postion.y * self.size_ratio as i32 ,
-            self.size_ratio,
-            self.size_ratio
-        );
-        return self.canvas.fill_rect(rectangle);
-    }
-
-
-    /// Redraws the whole game from scratch.
-    /// 
-    /// Avoid using this often
-    fn draw_refresh_all(&mut self) -> Result<(), String> {
-        self.canvas.clear(); // Erase everything
-
-        // Redraw board
-        for (yindex, y) in self.board.into_iter().enumerate() {
-            for (xindex, x) in y.into_iter().enumerate() {
-                if let Some(c) = x {
-                    self.canvas.set_draw_color(self.get_tetris_color(&c));
-                    self.draw_in_grid(Position {x: xindex as i32, y: yindex as i32})?;
-                } else {
-                    self.canvas.set_draw_color(Color::RGB(0, 0, 0));
-                    self.draw_in_grid(Position {x: xindex as i32, y: yindex as i32})?; 
-                }
-            }
-        }
-
-        self.draw_tetris()?;
-
-        Ok(())
-    }
-
-    /// Draws current_tetris at its current position with the color of your choice
-    fn draw_tetris_pick_color(&mut self, color: Color) -> Result<(), String> {
-        self.canvas.set_draw_color(color);
-        for (yindex, y) in self.current_tetris.map.into_iter().enumerate() {
-            for (xindex, x) in y.into_iter().enumerate() {
-                if x {
-                    self.draw_in_grid(Position {x :(xindex as i32 + self.current_tetris.position.x), y: (yindex as i32 + self.current_tetris.position.y)})?;
-                }
-                
-            }
-        }
-        Ok(())
-    }
-
-    /// Draws black current_tetris at its current position
-    fn erase_tetris(&mut self) -> Result<(), String> {
-        self.draw_tetris_pick_color(Color::RGB(0, 0, 0))
-    }
-
-    /// Draws current_tetris at its current position with its intended color
-    fn draw_tetris(&mut self) -> Result<(), String> {
-        self.draw_tetris_pick_color(self.current_tetris.color)
-    }
-
-    /// Check if tetris will hit board when shifted
-    fn check_tetris_hit_board(&self, tetris: &Tetris, shift: Position) -> bool {
-        let mut colided = false;
-        for (yindex, y) in tetris.map.into_iter().enumerate() {
-            for (xindex, x) in y.into_iter().enumerate() {
-                if x &&
-                    (yindex as i32 + shift.y + tetris.position.y) >= 0 &&
-                    (yindex as i32 + shift.y + tetris.position.y) < GAME_HEIGHT as i32 &&
-                    (xindex as i32 + shift.x + tetris.position.x) >= 0 &&
-                    (xindex as i32 + shift.x + tetris.position.x) < GAME_WIDTH as i32 {
-                    if self.board[(yindex as i32 + shift.y + tetris.position.y) as usize][(xindex as i32 + shift.x + tetris.position.x) as usize].is_some() {
-                        colided = true;
-                    }
-                }
-            }
-        }
-
-        colided
-    }
-
-    /// Check if tetris is hitting the board's walls
-    fn check_tetris_hit_wall(&self, tetris: &Tetris, shift: i32) -> bool {
-        tetris.position.x + tetris.most_left().unwrap_or_default() as i32 + shift < 0 ||
-        tetris.position.x + tetris.most_right().unwrap_or_default() as i32 + shift >= GAME_WIDTH as i32
-    }
-
-    /// Check if tetris reached the bottom of the board
-    fn check_tetris_hit_bottom(&self, tetris: &Tetris, shift: i32) -> bool {
-        tetris.position.y + tetris.most_bottom().unwrap() as i32 + shift >= GAME_HEIGHT as i32
-    }
-
-    /// Move the tetris while redrawing it
-    fn move_tetris(&mut self, shift: Position) -> Result<(), String> {
-        self.erase_tetris()?;
-        self.current_tetris.position.x += shift.x;
-        self.current_tetris.position.y += shift.y;
-        self.draw_tetris()?;
-        Ok(())
-    }
-
-    /// Move the tetris while redrawing it
-    /// 
-    /// Also check if the movement will hit something and cancels if it will something
-    fn move_tetris_with_check(&mut self, shift: Position) -> Result<(), String> {
-        if !self.check_tetris_hit_board(&self.current_tetris, shift) && !self.check_tetris_hit_wall(&self.current_tetris, shift.x) {
-            self.move_tetris(shift)?;
-        }
-
-        Ok(())
-    }
-
-    /// Rotates the tetris left
-    /// 
-    /// Cancels the action if it will hit something when the rotation is done
-    /// 
-    /// Note: If you need to rotate without checks use the tetris struct directly
-    fn rotate_tetris_left(&mut self) -> Result<(), String> {
-        let rotated_tetris = Tetris {
-            position: self.current_tetris.position,
-            tetris_type: self.current_tetris.tetris_type,
-            map: self.current_tetris.rotate_left_result(),
-            color: self.current_tetris.color
-        };
-
-        if !self.check_tetris_hit_board(&rotated_tetris, Position { x: 0, y: 0 }) && !self.check_tetris_hit_wall(&rotated_tetris, 0) {
-            self.erase_tetris()?;
-            self.current_tetris.rotate_left();
-            self.draw_tetris()?;
-        }
-        
-        Ok(())
-    }
-
-
-    /// Rotates the tetris left
-    /// 
-    /// Cancels the action if it will hit something if the rotation is done
-    /// 
-    /// Note: If you need to rotate without checks use the tetris struct directly
-    fn rotate_tetris_right(&mut self) -> Result<(), String> {
-        let rotated_tetris = Tetris {
-            position: self.current_tetris.position,
-            tetris_type: self.current_tetris.tetris_type,
-            map: self.current_tetris.rotate_right_result(),
-            color: self.current_tetris.color
-        };
-
-        if !self.check_tetris_hit_board(&rotated_tetris, Position { x: 0, y: 0 }) && !self.check_tetris_hit_wall(&rotated_tetris, 0) {
-            self.erase_tetris()?;
-            self.current_tetris.rotate_right();
-            self.draw_tetris()?;
-        }
-        
-        Ok(())
-    }
-
-    /// Insert current_tetris inside board
-    fn insert_tetris_in_map(&mut self) {
-        for (yindex, y) in self.current_tetris.map.into_iter().enumerate() {
-            for (xindex, x) in y.into_iter().enumerate() {
-                if x &&
-                    yindex as i32 + self.current_tetris.position.y >= 0 &&
-                    yindex as i32 + self.current_tetris.position.y < GAME_HEIGHT as i32 &&
-                    xindex as i32 + self.current_tetris.position.x >= 0 &&
-                    xindex as i32 + self.current_tetris.position.x < GAME_WIDTH as i32 {
-                    self.board[(yindex as i32 + self.current_tetris.position.y) as usize][(xindex as i32 + self.current_tetris.position.x) as usize] = Some(self.current_tetris.tetris_type);
-                }
-
-                if x && yindex as i32 + self.current_tetris.position.y < 0 {
-                    self.gameover = true;
-                }
-            }
-        } 
-    }
-
-
-    /// Finds all of the full lines in boards.
-    /// 
-    /// Return an array with the false or true for each line.
-    /// True if the line is full
-    /// 
-    /// Important to have it like this because only give the full line indexes would use HEAP
-    fn full_lines(&self) -> [bool; GAME_HEIGHT] {
-        let mut full_lines = [false; GAME_HEIGHT];
-        for (index, line) in self.board.into_iter().enumerate() {
-            if line.into_iter().find(|x| x.is_none()).is_none() {
-                full_lines[index] = true;
-            }
-        }
-
-        full_lines
-    }
-
-    /// Deletes the specified line and shift all lines above it
-    fn clear_line(&mut self, line_index: usize) {
-        // Shift lines todo: dont use clone here
-        for (index, line) in self.board.clone()[0..line_index].into_iter().enumerate() {
-            self.board[index + 1] = line.clone();
-        }
-        self.board[0] = [None; GAME_WIDTH]; // Remove top line
-    }
-}
-
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum TetrisType {
-    Line,
-    LShapeRight,
-    LShapeLeft,
-    ZShapeRight,
-    ZShapeLeft,
-    TShape,
-    Square,
-    Custom(TetrisMap)
-}
-
-/// Map type to make sure tetris management stays consistent
-type TetrisMap = [[bool; 5]; 5];
-
-const LINE_MAP: TetrisMap = [[false, false, false, false, false],
-                        [false, false, false, false, false],
-                        [false, true, true, true, true],
-                        [false, false, false, false, false],
-                        [false, false, false, false, false]];
-const LSHAPE_RIGHT_MAP: TetrisMap = [[false, false, false, false, false],
-                                [false, false, false, true, false],
-                                [false, true, true, true, false],
-                                [false, false, false, false, false],
-                                [false, false, false, false, false]];
-const LSHAPE_LEFT_MAP: TetrisMap = [[false, false, false, false, false],
-                                [false, false, false, false, false],
-                                [false, true, true, true, false],
-                                [false, false, false, true, false],
-                                [false, false, false, false, false]];
-const ZSHAPE_RIGHT_MAP: TetrisMap = [[false, false, false, false, false],
-                                [false, true, true, false, false],
-                                [false, false, true, true, false],
-                                [false, false, false, false, false],
-                                [false, false, false, false, false]];
-
-const ZSHAPE_LEFT_MAP: TetrisMap = [[false, false, false, false, false],
-                                [false, false, true, true, false],
-                                [false, true, true, false, false],
-                                [false, false, false, false, false],
-                                [false, false, false, false, false]];
-
-const TSHAPE_MAP: TetrisMap = [[false, false, false, false, false],
-                            [false, false, true, false, false],
-                            [false, true, true, true, false],
-                            [false, false, false, false, false],
-                            [false, false, false, false, false]];
-
-const SQUARE_MAP: TetrisMap = [[false, false, false, false, false],
-                                [false, false, true, true, false],
-                                [false, false, true, true, false],
-                                [false, false, false, false, false],
-                                [false, false, false, false, false]];
-
-
-fn tetris_type_to_map(tetris_type: &TetrisType) -> TetrisMap {
-    match tetris_type {
-        TetrisType::Line => LINE_MAP,
-        TetrisType::LShapeRight => LSHAPE_RIGHT_MAP,
-        TetrisType::LShapeLeft => LSHAPE_LEFT_MAP,
-        TetrisType::ZShapeRight => ZSHAPE_RIGHT_MAP,
-        TetrisType::ZShapeLeft => ZSHAPE_LEFT_MAP,
-        TetrisType::TShape => TSHAPE_MAP,
-        TetrisType::Square => SQUARE_MAP,
-        TetrisType::Custom(_) => [[false; 5]; 5] // Return an empty map
-    }
-}
-
-/// Contains the tetris props and transform logics
-struct Tetris {
-    /// Current tetris position
-    position: Position,
-    /// Current tetris type
-    tetris_type: TetrisType,
-    /// Contains the tetris shape
-    map: TetrisMap,
-    /// Tetris color overwrite
-    color: Color
-}
-
-impl Tetris {
-    /// Makes a new tetris object
-    /// Map is derived from the type.
-    /// If type is [`TetrisType::Custom`] it will use the map within the Enum
-    fn new(tetris_type: TetrisType, color: Color) -> Self {
-        let map = if let TetrisType::Custom(map) = tetris_type {
-            map
-        } else {
-            tetris_type_to_map(&tetris_type)
-        };
-
-        Self {
-            position: Position {x: (GAME_WIDTH as i32 / 2) - 2, y: -5},
-            tetris_type,
-            map,
-            color
-        }
-    }
-
-    /// Return the tetris map if it was rotated right
-    fn rotate_right_result(&self) -> TetrisMap {
-        let mut new_tetris: TetrisMap = [[false; 5]; 5];
-        
-        for (yindex, y) in self.map.iter().enumerate() {
-            for (xindex, x) in y.iter().enumerate() {
-                new_tetris[xindex][4 - yindex] = x.clone();
-            }
-        }
-
-        return new_tetris;
-    }
-
-    /// Return the tetris map if it was rotated left
-    fn rotate_left_result(&self) -> TetrisMap {
-        let mut new_tetris: TetrisMap = [[false; 5]; 5];
-        
-        for (yindex, y) in self.map.iter().enumerate() {
-            for (xindex, x) in y.iter().enumerate() {
-                new_tetris[4 - xindex][yindex] = x.clone();
-            }
-        }
-
-        return new_tetris;
-    }
-
-    /// Rotates the tetris map right directly
-    fn rotate_right(&mut self) {
-        self.map = self.rotate_right_result();
-    }
-
-    /// Rotates the tetris map left directly
-    fn rotate_left(&mut self) {
-        self.map = self.rotate_left_result();
-    }
-
-    /// Finds the most left square in map
-    /// 
-    /// returns the x index of it
-    fn most_left(&self) -> Option<usize> {
-        let mut pos: Option<usize> = None;
-        for y in self.map.iter() {
-            for (xindex, x) in y.iter().enumerate() {
-                if x.clone() && xindex < pos.unwrap_or(200) {
-                    pos = Some(xindex);
-                }
-            }
-        }
-
-        pos
-    }
-
-    /// Finds the most right square in map
-    /// 
-    /// returns the x index of it
-    fn most_right(&self) -> Option<usize> {
-        let mut pos: Option<usize> = None;
-        for y in self.map.iter() {
-            for (xindex, x) in y.iter().enumerate() {
-                if x.clone() && xindex > pos.unwrap_or(0) {
-                    pos = Some(xindex);
-                }
-            }
-        }
-
-        pos
-    }
-
-    /// Finds the most bottom square in map
-    /// 
-    /// returns the y index of it
-    fn most_bottom(&self) -> Option<usize> {
-        let mut pos: Option<usize> = None;
-        for (yindex, y) in self.map.iter().enumerate() {
-            if y.contains(&true) {
-                pos = Some(yindex);
-            }
-        }
-
-        pos
-    }
-}
\ No newline at end of file
@@ -0,0 +1,104 @@
+use sdl2::controller::Button;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+
+/// What the player did in a menu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuAction {
+    /// Item at this index was picked
+    Select(usize),
+    /// Item at this index was pushed left (-1) or right (1), used for values
+    Adjust(usize, i32),
+    /// Leave the menu
+    Back,
+    /// Window was closed
+    Quit
+}
+
+/// Vertical list of items navigable by keyboard and controller
+pub struct Menu {
+    /// Text of each item
+    pub items: Vec<String>,
+    /// Index of the highlighted item
+    pub selected: usize
+}
+
+impl Menu {
+    pub fn new(items: &[&str]) -> Self {
+        Self {
+            items: items.iter().map(|item| item.to_string()).collect(),
+            selected: 0
+        }
+    }
+
+    /// Moves the selection or returns what was done with it
+    pub fn handle_event(&mut self, event: &Event) -> Option<MenuAction> {
+        match event {
+            Event::Quit { .. } => Some(MenuAction::Quit),
+            Event::KeyDown { keycode: Some(Keycode::Up), .. } | Event::ControllerButtonDown { button: Button::DPadUp, .. } => {
+                self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+                None
+            },
+            Event::KeyDown { keycode: Some(Keycode::Down), .. } | Event::ControllerButtonDown { button: Button::DPadDown, .. } => {
+                self.selected = (self.selected + 1) % self.items.len();
+                None
+            },
+            Event::KeyDown { keycode: Some(Keycode::Left), .. } | Event::ControllerButtonDown { button: Button::DPadLeft, .. } => {
+                Some(MenuAction::Adjust(self.selected, -1))
+            },
+            Event::KeyDown { keycode: Some(Keycode::Right), .. } | Event::ControllerButtonDown { button: Button::DPadRight, .. } => {
+                Some(MenuAction::Adjust(self.selected, 1))
+            },
+            Event::KeyDown { keycode: Some(Keycode::Return | Keycode::KpEnter | Keycode::Space), repeat: false, .. } |
+            Event::ControllerButtonDown { button: Button::A, .. } => {
+                Some(MenuAction::Select(self.selected))
+            },
+            Event::KeyDown { keycode: Some(Keycode::Escape), repeat: false, .. } |
+            Event::ControllerButtonDown { button: Button::B | Button::Back, .. } => {
+                Some(MenuAction::Back)
+            },
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::keyboard::Mod;
+
+    use super::*;
+
+    fn key(keycode: Keycode, repeat: bool) -> Event {
+        Event::KeyDown { timestamp: 0, window_id: 0, keycode: Some(keycode), scancode: None, keymod: Mod::NOMOD, repeat }
+    }
+
+    fn button(button: Button) -> Event {
+        Event::ControllerButtonDown { timestamp: 0, which: 0, button }
+    }
+
+    #[test]
+    fn selection_wraps_around() {
+        let mut menu = Menu::new(&["Play", "Settings", "Quit"]);
+
+        assert_eq!(menu.handle_event(&key(Keycode::Up, false)), None);
+        assert_eq!(menu.selected, 2);
+        menu.handle_event(&button(Button::DPadDown));
+        assert_eq!(menu.selected, 0);
+        menu.handle_event(&key(Keycode::Down, true));
+        assert_eq!(menu.handle_event(&button(Button::A)), Some(MenuAction::Select(1)));
+    }
+
+    #[test]
+    fn actions_from_keys_and_buttons() {
+        let mut menu = Menu::new(&["Level"]);
+
+        assert_eq!(menu.handle_event(&key(Keycode::Left, true)), Some(MenuAction::Adjust(0, -1)));
+        assert_eq!(menu.handle_event(&button(Button::DPadRight)), Some(MenuAction::Adjust(0, 1)));
+        assert_eq!(menu.handle_event(&key(Keycode::Return, false)), Some(MenuAction::Select(0)));
+        assert_eq!(menu.handle_event(&button(Button::B)), Some(MenuAction::Back));
+        assert_eq!(menu.handle_event(&Event::Quit { timestamp: 0 }), Some(MenuAction::Quit));
+        // Holding a key doesn't pick or leave again
+        assert_eq!(menu.handle_event(&key(Keycode::Return, true)), None);
+        assert_eq!(menu.handle_event(&key(Keycode::Escape, true)), None);
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use crate::game::{GameEvent, GameSettings, Input};
+use crate::hash::fnv1a;
+use crate::replay::{parse_debug_name, Replay};
+use crate::versus::{Versus, PLAYERS};
+
+/// First line sent by both sides, bumped when the protocol changes
+const NET_HEADER: &str = "tetris-net 1";
+/// Ticks between the moment an input is given and the tick it is played at, hides the network latency
+pub const INPUT_DELAY: u64 = 3;
+/// Ticks between two comparisons of the game state hashes
+pub const HASH_INTERVAL: u64 = 60;
+/// Longest wait for the other side during the handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Which side of a network match to play
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetRole {
+    /// Waits for the other player on this port, picks the seed and settings and plays player 1
+    Host(u16),
+    /// Connects to a host at this address, like `192.168.1.20:7777`, and plays player 2
+    Join(String)
+}
+
+/// Versus match against a remote player over TCP
+///
+/// Both sides run the same deterministic [`Versus`] in lockstep. Each side sends its inputs for every tick,
+/// stamped with the tick they are played at, [`INPUT_DELAY`] ticks after they were given.
+/// A tick is only played once the inputs of both players for it are known.
+/// Every [`HASH_INTERVAL`] ticks both sides send a hash of the games to catch a desync
+///
+/// The protocol is text, one message per line:
+/// - `tick <N> <Input>...` inputs of the sender for tick N, sent for every tick even without inputs
+/// - `hash <N> <HASH>` hash of the games after tick N
+/// - `bye` the sender left the match
+pub struct NetSession {
+    stream: TcpStream,
+    /// Player played on this side, the host is 0
+    local_player: usize,
+    versus: Versus,
+    /// Inputs of each player by tick, removed once played
+    inputs: [BTreeMap<u64, Vec<Input>>; PLAYERS],
+    /// Local inputs given since the last tick was sent
+    queued: Vec<Input>,
+    /// Next tick the local inputs are sent for
+    next_local_tick: u64,
+    /// Next tick the other side will send inputs for, every tick before it is known
+    next_remote_tick: u64,
+    /// Hashes of each side by tick, compared once both are there
+    hashes: [BTreeMap<u64, u64>; PLAYERS],
+    /// Received bytes not making a full line yet
+    incoming: Vec<u8>,
+    /// Bytes the socket didn't take yet
+    outgoing: Vec<u8>,
+    /// True once the other side said bye or closed the connection, the ticks it already sent can still be played
+    peer_left: bool
+}
+
+impl NetSession {
+    /// Accepts a player on the listener and sends them the seed and settings of the match
+    ///
+    /// Returns Ok(None) while nobody connected when the listener is non blocking
+    pub fn host(listener: &TcpListener, seed: u64, settings: GameSettings) -> Result<Option<Self>, String> {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+            Err(e) => return Err(format!("can't accept a player: {}", e))
+        };
+        // Some platforms give the accepted socket the non blocking mode of the listener
+        stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).map_err(|e| e.to_string())?;
+
+        let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+        let header = read_line(&mut reader)?;
+        if header != NET_HEADER {
+            return Err(format!("the other player speaks \"{}\", expected \"{}\"", header, NET_HEADER));
+        }
+
+        // The settings go in the replay format, its header and inputs line included
+        let hello = format!("{}\n{}", NET_HEADER, Replay::new(seed, settings).to_text());
+        (&stream).write_all(hello.as_bytes()).map_err(|e| format!("can't send the match settings: {}", e))?;
+
+        Self::start(stream, reader.buffer().to_vec(), 0, seed, settings).map(Some)
+    }
+
+    /// Connects to a host and gets the seed and settings of the match from it
+    pub fn join(address: &str) -> Result<Self, String> {
+        let stream = TcpStream::connect(address).map_err(|e| format!("can't connect to {}: {}", address, e))?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).map_err(|e| e.to_string())?;
+        (&stream).write_all(format!("{}\n", NET_HEADER).as_bytes()).map_err(|e| e.to_string())?;
+
+        let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+        let header = read_line(&mut reader)?;
+        if header != NET_HEADER {
+            return Err(format!("the host speaks \"{}\", expected \"{}\"", header, NET_HEADER));
+        }
+
+        let mut text = String::new();
+        loop {
+            let line = read_line(&mut reader)?;
+            text.push_str(&line);
+            text.push('\n');
+            if line == "inputs" {
+                break;
+            }
+        }
+        let replay = Replay::from_text(&text).map_err(|e| format!("invalid match settings: {}", e))?;
+
+        // The first ticks of the host can already be in the reader
+        Self::start(stream, reader.buffer().to_vec(), 1, replay.seed, replay.settings)
+    }
+
+    fn start(stream: TcpStream, incoming: Vec<u8>, local_player: usize, seed: u64, settings: GameSettings) -> Result<Self, String> {
+        stream.set_read_timeout(None).map_err(|e| e.to_string())?;
+        stream.set_nonblocking(true).map_err(|e| e.to_string())?;
+        // Messages are tiny and sent every tick, don't wait to fill a packet
+        stream.set_nodelay(true).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            stream,
+            local_player,
+            versus: Versus::new(seed, settings),
+            inputs: [BTreeMap::new(), BTreeMap::new()],
+            queued: Vec::new(),
+            // Nobody has inputs in the first ticks
+            next_local_tick: INPUT_DELAY,
+            next_remote_tick: INPUT_DELAY,
+            hashes: [BTreeMap::new(), BTreeMap::new()],
+            incoming,
+            outgoing: Vec::new(),
+            peer_left: false
+        })
+    }
+
+    pub fn versus(&self) -> &Versus {
+        &self.versus
+    }
+
+    /// Player of this side, 0 for the host and 1 for the one who joined
+    pub fn local_player(&self) -> usize {
+        self.local_player
+    }
+
+    /// Events of the player's game since the last call, see [`Versus::drain_events`]
+    pub fn drain_events(&mut self, player: usize) -> Vec<GameEvent> {
+        self.versus.drain_events(player)
+    }
+
+    /// Queues an input of the local player, it is played [`INPUT_DELAY`] ticks later on both sides
+    pub fn give_input(&mut self, input: Input) {
+        self.queued.push(input);
+    }
+
+    /// Exchanges inputs with the other side and plays the next tick if both inputs are known, call it once per frame
+    ///
+    /// Returns true if a tick was played, false while waiting for the other side or once the match is over.
+    /// Fails on a desync or when the other side left
+    pub fn update(&mut self) -> Result<bool, String> {
+        self.receive()?;
+        if self.versus.result().is_some() {
+            self.flush()?;
+            return Ok(false);
+        }
+
+        let tick = self.versus.game(0).ticks();
+        if self.next_local_tick <= tick + INPUT_DELAY {
+            let inputs = std::mem::take(&mut self.queued);
+            let mut message = format!("tick {}", self.next_local_tick);
+            for input in &inputs {
+                message.push_str(&format!(" {:?}", input));
+            }
+            self.send(&message);
+            self.inputs[self.local_player].insert(self.next_local_tick, inputs);
+            self.next_local_tick += 1;
+        }
+
+        let ticked = self.next_remote_tick > tick;
+        if !ticked && self.peer_left {
+            return Err("the other player left".to_string());
+        }
+        if ticked {
+            for player in 0..PLAYERS {
+                for input in self.inputs[player].remove(&tick).unwrap_or_default() {
+                    self.versus.handle_input(player, input);
+                }
+            }
+            self.versus.tick();
+
+            let tick = tick + 1;
+            if tick.is_multiple_of(HASH_INTERVAL) {
+                let hash = state_hash(&self.versus);
+                self.send(&format!("hash {} {:016x}", tick, hash));
+                self.hashes[self.local_player].insert(tick, hash);
+                self.compare_hashes()?;
+            }
+        }
+
+        self.flush()?;
+        Ok(ticked)
+    }
+
+    /// Tells the other side the match is over for this one. Nothing is sent if the socket is already gone
+    pub fn leave(&mut self) {
+        self.send("bye");
+        let _ = self.flush();
+    }
+
+    fn send(&mut self, message: &str) {
+        self.outgoing.extend_from_slice(message.as_bytes());
+        self.outgoing.push(b'\n');
+    }
+
+    /// Writes what the socket takes, the rest waits for the next call
+    fn flush(&mut self) -> Result<(), String> {
+        if self.peer_left {
+            self.outgoing.clear();
+        }
+
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err("the other player left".to_string()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e) => return Err(format!("connection lost: {}", e))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads every message already received
+    fn receive(&mut self) -> Result<(), String> {
+        let mut buffer = [0; 4096];
+        while !self.peer_left {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => self.peer_left = true,
+                Ok(read) => self.incoming.extend_from_slice(&buffer[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e) => return Err(format!("connection lost: {}", e))
+            }
+        }
+
+        while let Some(end) = self.incoming.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.incoming.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            self.handle_message(line.trim())?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_message(&mut self, message: &str) -> Result<(), String> {
+        let remote_player = PLAYERS - 1 - self.local_player;
+        let mut words = message.split_whitespace();
+        let number = |word: Option<&str>| word
+            .and_then(|word| word.parse::<u64>().ok())
+            .ok_or(format!("invalid message \"{}\"", message));
+
+        match words.next() {
+            Some("tick") => {
+                let tick = number(words.next())?;
+                if tick != self.next_remote_tick {
+                    return Err(format!("expected inputs for tick {}, got tick {}", self.next_remote_tick, tick));
+                }
+
+                let inputs = words
+                    .map(|name| parse_debug_name(&Input::ALL, name).ok_or(format!("tick {}: unknown input \"{}\"", tick, name)))
+                    .collect::<Result<Vec<Input>, String>>()?;
+                self.inputs[remote_player].insert(tick, inputs);
+                self.next_remote_tick += 1;
+            },
+            Some("hash") => {
+                let tick = number(words.next())?;
+                let hash = words
+                    .next()
+                    .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+                    .ok_or(format!("invalid message \"{}\"", message))?;
+                self.hashes[remote_player].insert(tick, hash);
+                self.compare_hashes()?;
+            },
+            Some("bye") => self.peer_left = true,
+            _ => return Err(format!("unknown message \"{}\"", message))
+        }
+
+        Ok(())
+    }
+
+    /// Compares the hashes both sides have, then forgets them
+    fn compare_hashes(&mut self) -> Result<(), String> {
+        let [first, second] = &mut self.hashes;
+        let common: Vec<u64> = first.keys().filter(|tick| second.contains_key(tick)).copied().collect();
+
+        for tick in common {
+            if first.remove(&tick) != second.remove(&tick) {
+                return Err(format!("desync at tick {}, the games are no longer the same on both sides", tick));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash of what matters in both games: boards, falling tetris, scores and pending garbage
+pub fn state_hash(versus: &Versus) -> u64 {
+    let mut text = String::new();
+    for player in 0..PLAYERS {
+        let game = versus.game(player);
+        let tetris = game.current_tetris();
+        text.push_str(&format!(
+            "{:?}{:?}{:?}{:?}{}{}",
+            game.board(), tetris.tetris_type, tetris.position, tetris.rotation, game.score(), game.pending_garbage()
+        ));
+    }
+
+    fnv1a(text.as_bytes())
+}
+
+/// Reads a line during the handshake, without its line break
+fn read_line(reader: &mut impl BufRead) -> Result<String, String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => Err("the other player left during the handshake".to_string()),
+        Ok(_) => Ok(line.trim().to_string()),
+        Err(e) => Err(format!("handshake failed: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    /// Ticks both sides play, a few hash comparisons
+    const MATCH_TICKS: u64 = 10 * HASH_INTERVAL;
+
+    /// Plays the side until the match ends or reaches [`MATCH_TICKS`], dropping the tetris every `every` ticks
+    /// after an input of its own so both sides send something different
+    fn play(mut session: NetSession, every: u64, input: Input) -> NetSession {
+        let player = session.local_player();
+        while session.versus().result().is_none() && session.versus().game(0).ticks() < MATCH_TICKS {
+            if !session.update().unwrap() {
+                thread::yield_now();
+                continue;
+            }
+
+            if session.versus().game(player).ticks().is_multiple_of(every) {
+                session.give_input(input);
+                session.give_input(Input::HardDrop);
+            }
+        }
+
+        session
+    }
+
+    #[test]
+    fn both_sides_play_the_same_match() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let settings = GameSettings { queue_length: 3, ..Default::default() };
+
+        let host = thread::spawn(move || {
+            let session = NetSession::host(&listener, 77, settings).unwrap().unwrap();
+            play(session, 20, Input::MoveLeft)
+        });
+        let joiner = thread::spawn(move || play(NetSession::join(&address).unwrap(), 25, Input::RotateRight));
+        let (host, joiner) = (host.join().unwrap(), joiner.join().unwrap());
+
+        assert_eq!(joiner.versus().seed(), 77);
+        assert_eq!(joiner.versus().game(0).settings().queue_length, 3);
+        assert_eq!(host.versus().game(0).ticks(), joiner.versus().game(0).ticks());
+        assert_eq!(state_hash(host.versus()), state_hash(joiner.versus()));
+        assert_eq!(host.versus().result(), joiner.versus().result());
+        // Both players really played
+        assert!(host.versus().game(0).stats().pieces > 5);
+        assert!(host.versus().game(1).stats().pieces > 5);
+    }
+
+    #[test]
+    fn joiner_speaking_another_protocol_is_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let joiner = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(b"tetris-net 0\n").unwrap();
+            // Keeps the socket open until the host answered
+            let _ = stream.read(&mut [0; 16]);
+        });
+        assert!(NetSession::host(&listener, 1, GameSettings::default()).is_err());
+        joiner.join().unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::{GameControllerSubsystem, Sdl};
+use tetris_sdl_rs::PLAYERS;
+
+/// SDL event pump that also opens game controllers when they are plugged in and closes them when they are removed
+///
+/// Screens poll it like the SDL one, device events are still given to them
+pub struct EventPump {
+    pump: sdl2::EventPump,
+    subsystem: GameControllerSubsystem,
+    /// Opened controllers, kept open as long as they are here
+    pads: Vec<GameController>,
+    players: PlayerSlots
+}
+
+/// Instance id of the controller of each versus player
+///
+/// A player keeps their controller when another one is removed, a new controller takes the first free slot
+#[derive(Default)]
+struct PlayerSlots([Option<u32>; PLAYERS]);
+
+impl EventPump {
+    /// Opens every controller already plugged in
+    pub fn new(sdl_context: &Sdl) -> Result<Self, String> {
+        let mut event_pump = Self {
+            pump: sdl_context.event_pump()?,
+            subsystem: sdl_context.game_controller()?,
+            pads: Vec::new(),
+            players: PlayerSlots::default()
+        };
+
+        let available = event_pump.subsystem
+            .num_joysticks()
+            .map_err(|e| format!("can't enumerate joysticks: {}", e))?;
+        for index in 0..available {
+            event_pump.open(index);
+        }
+
+        Ok(event_pump)
+    }
+
+    /// Pending events, controllers are opened or closed before being returned
+    pub fn poll_iter(&mut self) -> std::vec::IntoIter<Event> {
+        let events: Vec<Event> = self.pump.poll_iter().collect();
+        for event in &events {
+            match event {
+                Event::ControllerDeviceAdded { which, .. } => self.open(*which),
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    if let Some(index) = self.pads.iter().position(|pad| pad.instance_id() == *which) {
+                        println!("Controller \"{}\" disconnected", self.pads.remove(index).name());
+                    }
+                    self.players.free(*which);
+                },
+                _ => {}
+            }
+        }
+
+        events.into_iter()
+    }
+
+    /// Versus player the controller belongs to, None for controllers plugged in while both players had one
+    pub fn player_of(&self, which: u32) -> Option<usize> {
+        self.players.player_of(which)
+    }
+
+    /// Opens the controller at the joystick index, SDL also reports the ones plugged in at startup so it can already be open
+    fn open(&mut self, index: u32) {
+        if !self.subsystem.is_game_controller(index) {
+            println!("{} is not a game controller", index);
+            return;
+        }
+
+        match self.subsystem.open(index) {
+            Ok(pad) => {
+                if !self.pads.iter().any(|opened| opened.instance_id() == pad.instance_id()) {
+                    println!("Controller \"{}\" connected", pad.name());
+                    self.players.assign(pad.instance_id());
+                    self.pads.push(pad);
+                }
+            },
+            Err(e) => println!("Can't open controller {}: {:?}", index, e)
+        }
+    }
+}
+
+impl PlayerSlots {
+    /// Gives the controller the first free slot, if any
+    fn assign(&mut self, which: u32) {
+        if let Some(slot) = self.0.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(which);
+        }
+    }
+
+    /// Frees the slot of a removed controller, the other players keep theirs
+    fn free(&mut self, which: u32) {
+        if let Some(slot) = self.0.iter_mut().find(|slot| **slot == Some(which)) {
+            *slot = None;
+        }
+    }
+
+    fn player_of(&self, which: u32) -> Option<usize> {
+        self.0.iter().position(|slot| *slot == Some(which))
+    }
+}
+
+/// Instance id of the controller that sent the event, None for keyboard and window events
+pub fn controller_of(event: &Event) -> Option<u32> {
+    match event {
+        Event::ControllerButtonDown { which, .. } |
+        Event::ControllerButtonUp { which, .. } |
+        Event::ControllerAxisMotion { which, .. } => Some(*which),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn players_keep_their_controller() {
+        let mut slots = PlayerSlots::default();
+        for which in [4, 7, 9] {
+            slots.assign(which);
+        }
+        assert_eq!((slots.player_of(4), slots.player_of(7), slots.player_of(9)), (Some(0), Some(1), None));
+
+        // Player 2 doesn't become player 1 when player 1 unplugs
+        slots.free(4);
+        assert_eq!((slots.player_of(4), slots.player_of(7)), (None, Some(1)));
+
+        slots.assign(12);
+        assert_eq!((slots.player_of(12), slots.player_of(7)), (Some(0), Some(1)));
+    }
+
+    #[test]
+    fn keyboard_events_have_no_controller() {
+        let button = Event::ControllerButtonDown { timestamp: 0, which: 3, button: sdl2::controller::Button::A };
+        assert_eq!(controller_of(&button), Some(3));
+        assert_eq!(controller_of(&Event::Quit { timestamp: 0 }), None);
+    }
+}
@@ -0,0 +1,372 @@
+use rand::rngs::SmallRng;
+use rand::{RngCore, SeedableRng};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::ttf::Font;
+use sdl2::video::Window;
+
+use crate::menu::Menu;
+use tetris_sdl_rs::{
+    GameMode, Position, Tetris, TetrisGame, TetrisType, Versus, BUFFER_HEIGHT, NATURAL_TETRIS, PLAYERS, TICKS_PER_SECOND, ULTRA_TICKS
+};
+
+/// Width of the side panel on the right of the board, in board squares
+pub const SIDE_PANEL_WIDTH: usize = 4;
+/// Space between the side panel sections, in pixel
+const PANEL_MARGIN: i32 = 10;
+
+/// Draws a [`TetrisGame`] on a SDL canvas
+///
+/// Only reads the game state, never changes it
+pub struct Renderer<'a> {
+    /// surface to draw on
+    pub canvas: &'a mut Canvas<Window>,
+    /// Font used for the HUD
+    font: &'a Font<'a, 'static>,
+    /// Number of pixel each board squares uses
+    size_ratio: u32,
+    /// Pixel column the board starts at, moved to draw several games side by side
+    origin_x: i32
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(canvas: &'a mut Canvas<Window>, font: &'a Font<'a, 'static>, size_ratio: u32) -> Self {
+        Self {
+            canvas,
+            font,
+            size_ratio,
+            origin_x: 0
+        }
+    }
+
+    /// Main draw function for the game grid
+    ///
+    /// Draws a single square based on the position and uses ratio for each square size
+    fn draw_in_grid(&mut self, postion: Position) -> Result<(), String> {
+        let rectangle = Rect::new(
+            self.origin_x + postion.x * self.size_ratio as i32,
+            postion.y * self.size_ratio as i32,
+            self.size_ratio,
+            self.size_ratio
+        );
+        self.canvas.fill_rect(rectangle)
+    }
+
+    /// Redraws the whole game from scratch and presents it
+    pub fn draw(&mut self, game: &TetrisGame) -> Result<(), String> {
+        self.draw_game(game)?;
+
+        self.canvas.present();
+        Ok(())
+    }
+
+    /// Redraws the game with a status line at the bottom of the board, used while watching a replay
+    pub fn draw_with_status(&mut self, game: &TetrisGame, status: &str) -> Result<(), String> {
+        self.draw_game(game)?;
+
+        let (_, height) = self.canvas.output_size()?;
+        let y = height as i32 - self.font.height() - PANEL_MARGIN;
+        self.draw_text(status, PANEL_MARGIN, y, Color::RGB(255, 220, 0))?;
+
+        self.canvas.present();
+        Ok(())
+    }
+
+    /// Draws both games of a versus match next to each other and presents them, with an optional status line at the bottom
+    pub fn draw_versus(&mut self, versus: &Versus, status: Option<&str>) -> Result<(), String> {
+        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+        self.canvas.clear();
+
+        for player in 0..PLAYERS {
+            let game = versus.game(player);
+            self.draw_board(game)?;
+
+            // The next game starts after the side panel of this one
+            self.origin_x += ((game.board().width() + SIDE_PANEL_WIDTH) as u32 * self.size_ratio) as i32;
+            let (_, height) = self.canvas.output_size()?;
+            self.canvas.set_draw_color(Color::RGB(80, 80, 80));
+            self.canvas.fill_rect(Rect::new(self.origin_x - 2, 0, 2, height))?;
+        }
+        self.origin_x = 0;
+
+        if let Some(status) = status {
+            let (_, height) = self.canvas.output_size()?;
+            let y = height as i32 - self.font.height() - PANEL_MARGIN;
+            self.draw_text(status, PANEL_MARGIN, y, Color::RGB(255, 220, 0))?;
+        }
+
+        self.canvas.present();
+        Ok(())
+    }
+
+    /// Draws the board, the tetris and the side panel without presenting
+    fn draw_game(&mut self, game: &TetrisGame) -> Result<(), String> {
+        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+        self.canvas.clear(); // Erase everything
+
+        self.draw_board(game)
+    }
+
+    /// Draws the board, the tetris and the side panel at origin_x, over what is already there
+    fn draw_board(&mut self, game: &TetrisGame) -> Result<(), String> {
+        // Redraw board, the hidden buffer stays hidden
+        for (yindex, y) in game.board().visible_rows().enumerate() {
+            for (xindex, x) in y.iter().enumerate() {
+                if let Some(c) = x {
+                    self.canvas.set_draw_color(get_tetris_color(c, game.lines()));
+                    self.draw_in_grid(Position {x: xindex as i32, y: yindex as i32})?;
+                }
+            }
+        }
+
+        let current_tetris = game.current_tetris();
+        let color = get_tetris_color(&current_tetris.tetris_type, game.lines());
+
+        // Ghost first so the tetris is drawn over it when they overlap
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.draw_tetris(&game.ghost_tetris(), Color::RGBA(color.r, color.g, color.b, 70))?;
+        self.canvas.set_blend_mode(BlendMode::None);
+
+        self.draw_tetris(current_tetris, color)?;
+
+        self.draw_side_panel(game)?;
+        self.draw_garbage_meter(game)
+    }
+
+    /// Draws the pending garbage as a red bar rising from the bottom of the separator
+    fn draw_garbage_meter(&mut self, game: &TetrisGame) -> Result<(), String> {
+        let lines = game.pending_garbage().min(game.board().visible_height()) as u32;
+        if lines == 0 {
+            return Ok(());
+        }
+
+        let x = self.origin_x + (game.board().width() as u32 * self.size_ratio) as i32;
+        let height = lines * self.size_ratio;
+        let bottom = game.board().visible_height() as u32 * self.size_ratio;
+        let width = (self.size_ratio / 4).max(2);
+
+        self.canvas.set_draw_color(Color::RGB(220, 40, 40));
+        self.canvas.fill_rect(Rect::new(x - width as i32 / 2, (bottom - height) as i32, width, height))
+    }
+
+    /// Draws the hold slot, the upcoming tetris and the HUD on the right of the board
+    fn draw_side_panel(&mut self, game: &TetrisGame) -> Result<(), String> {
+        let panel_x = self.origin_x + (game.board().width() as u32 * self.size_ratio) as i32;
+        let text_x = panel_x + PANEL_MARGIN;
+        let line_height = self.font.height();
+        let (_, panel_height) = self.canvas.output_size()?;
+
+        // Separator between board and panel
+        self.canvas.set_draw_color(Color::RGB(80, 80, 80));
+        self.canvas.fill_rect(Rect::new(panel_x, 0, 2, panel_height))?;
+
+        // Hold slot, grayed out when it can't be used
+        let mut y = PANEL_MARGIN;
+        self.draw_text("HOLD", text_x, y, Color::RGB(200, 200, 200))?;
+        y += line_height;
+        if let Some(held) = game.held_tetris() {
+            let color = if game.can_hold() {
+                get_tetris_color(&held, game.lines())
+            } else {
+                Color::RGB(80, 80, 80)
+            };
+            self.draw_preview(&held, panel_x, y, color)?;
+        }
+        y += self.preview_height() + PANEL_MARGIN;
+        self.draw_panel_separator(panel_x, y)?;
+
+        // Upcoming tetris
+        y += PANEL_MARGIN;
+        self.draw_text("NEXT", text_x, y, Color::RGB(200, 200, 200))?;
+        y += line_height;
+        for tetris_type in game.queue().iter() {
+            self.draw_preview(tetris_type, panel_x, y, get_tetris_color(tetris_type, game.lines()))?;
+            y += self.preview_height();
+        }
+        y += PANEL_MARGIN;
+        self.draw_panel_separator(panel_x, y)?;
+
+        // HUD
+        y += PANEL_MARGIN;
+        // Ultra counts down, the other modes count up
+        let time = if game.settings().mode == GameMode::Ultra {
+            ULTRA_TICKS.saturating_sub(game.ticks())
+        } else {
+            game.ticks()
+        };
+        let hud = [
+            ("SCORE", game.score().to_string()),
+            ("LEVEL", game.level().to_string()),
+            ("LINES", game.lines().to_string()),
+            ("TIME", format_time(time))
+        ];
+        for (label, value) in hud {
+            self.draw_text(label, text_x, y, Color::RGB(200, 200, 200))?;
+            y += line_height;
+            self.draw_text(&value, text_x, y, Color::RGB(255, 255, 255))?;
+            y += line_height + PANEL_MARGIN;
+        }
+
+        Ok(())
+    }
+
+    /// Draws a full screen menu with a title on top and presents it
+    ///
+    /// Used for the pause overlay too, the board stays hidden behind it so nobody can peek
+    pub fn draw_menu(&mut self, title: &str, menu: &Menu) -> Result<(), String> {
+        self.draw_page(title, &[], Some(menu))
+    }
+
+    /// Draws a full screen page with a title, some lines of text and an optional menu under them, then presents it
+    pub fn draw_page(&mut self, title: &str, lines: &[String], menu: Option<&Menu>) -> Result<(), String> {
+        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+        self.canvas.clear();
+
+        let (_, height) = self.canvas.output_size()?;
+        let line_height = self.font.height() + PANEL_MARGIN;
+        let mut y = height as i32 / 6;
+
+        self.draw_text_centered(title, y, Color::RGB(255, 255, 255))?;
+        y += line_height * 2;
+
+        for line in lines {
+            self.draw_text_centered(line, y, Color::RGB(200, 200, 200))?;
+            y += line_height;
+        }
+        if !lines.is_empty() {
+            y += line_height;
+        }
+
+        let Some(menu) = menu else {
+            self.canvas.present();
+            return Ok(());
+        };
+
+        for (index, item) in menu.items.iter().enumerate() {
+            if index == menu.selected {
+                self.draw_text_centered(&format!("> {} <", item), y, Color::RGB(255, 220, 0))?;
+            } else {
+                self.draw_text_centered(item, y, Color::RGB(200, 200, 200))?;
+            }
+            y += line_height;
+        }
+
+        self.canvas.present();
+        Ok(())
+    }
+
+    /// Draws text horizontally centered in the window
+    pub fn draw_text_centered(&mut self, text: &str, y: i32, color: Color) -> Result<(), String> {
+        let (width, _) = self.canvas.output_size()?;
+        let (text_width, _) = self.font.size_of(text).map_err(|e| e.to_string())?;
+        self.draw_text(text, (width as i32 - text_width as i32) / 2, y, color)
+    }
+
+    /// Draws an horizontal line across the side panel
+    fn draw_panel_separator(&mut self, panel_x: i32, y: i32) -> Result<(), String> {
+        self.canvas.set_draw_color(Color::RGB(80, 80, 80));
+        self.canvas.fill_rect(Rect::new(panel_x, y, SIDE_PANEL_WIDTH as u32 * self.size_ratio, 2))
+    }
+
+    /// Draws text with its top left corner at a pixel position
+    pub fn draw_text(&mut self, text: &str, x: i32, y: i32, color: Color) -> Result<(), String> {
+        let surface = self.font
+            .render(text)
+            .blended(color)
+            .map_err(|e| e.to_string())?;
+
+        let texture_creator = self.canvas.texture_creator();
+        let texture = texture_creator
+            .create_texture_from_surface(&surface)
+            .map_err(|e| e.to_string())?;
+
+        self.canvas.copy(&texture, None, Some(Rect::new(x, y, surface.width(), surface.height())))
+    }
+
+    /// Height in pixel of a side panel tetris, spawn orientations only use 3 rows of their map
+    fn preview_height(&self) -> i32 {
+        (self.preview_size() * 3) as i32
+    }
+
+    /// Size in pixel of the squares of the side panel tetris
+    fn preview_size(&self) -> u32 {
+        self.size_ratio * SIDE_PANEL_WIDTH as u32 / 8
+    }
+
+    /// Draws a tetris in its spawn orientation at a pixel position, using [`Renderer::preview_size`] squares
+    ///
+    /// The empty first row of the map is skipped
+    fn draw_preview(&mut self, tetris_type: &TetrisType, x: i32, y: i32, color: Color) -> Result<(), String> {
+        let map = Tetris::new(*tetris_type).map;
+        let size = self.preview_size();
+
+        // Maps are 5 squares wide, center them in the panel
+        let offset_x = x + ((SIDE_PANEL_WIDTH as u32 * self.size_ratio - size * 5) / 2) as i32;
+
+        self.canvas.set_draw_color(color);
+        for (yindex, row) in map.into_iter().enumerate() {
+            for (xindex, filled) in row.into_iter().enumerate() {
+                if filled {
+                    self.canvas.fill_rect(Rect::new(
+                        offset_x + (xindex as u32 * size) as i32,
+                        y + (yindex as i32 - 1) * size as i32,
+                        size,
+                        size
+                    ))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draws a tetris at its position with the color of your choice
+    ///
+    /// Squares still in the buffer end up above the window
+    fn draw_tetris(&mut self, tetris: &Tetris, color: Color) -> Result<(), String> {
+        self.canvas.set_draw_color(color);
+        for (yindex, y) in tetris.map.into_iter().enumerate() {
+            for (xindex, x) in y.into_iter().enumerate() {
+                if x {
+                    self.draw_in_grid(Position {x: (xindex as i32 + tetris.position.x), y: (yindex as i32 + tetris.position.y - BUFFER_HEIGHT as i32)})?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats a number of ticks as minutes, seconds and hundredths
+pub fn format_time(ticks: u64) -> String {
+    let hundredths = ticks * 100 / TICKS_PER_SECOND as u64;
+    format!("{}:{:02}.{:02}", hundredths / 6000, (hundredths / 100) % 60, hundredths % 100)
+}
+
+/// Get the color related to each tetris based on the number of lines cleared
+/// All custom tetris are white by default, garbage is gray
+pub fn get_tetris_color(tetris_type: &TetrisType, lines: usize) -> Color {
+    if *tetris_type == TetrisType::Garbage {
+        return Color::RGB(110, 110, 110);
+    }
+
+    // Buffer that contains the RGB of each tetris
+    let mut color_buff = [0; NATURAL_TETRIS.len() * 3];
+
+    // Fill buff with colors for every types
+    // Always the same for every games
+    SmallRng::seed_from_u64(lines as u64)
+        .fill_bytes(&mut color_buff);
+
+    // Find the index of the tetris based on NATURAL_TETRIS
+    let tetris_index = NATURAL_TETRIS
+        .iter()
+        .position(|x| x == tetris_type);
+
+    // A bit hacky but does the job
+    if let Some(index) = tetris_index {
+        Color::RGB(color_buff[index * 3], color_buff[(index * 3) + 1], color_buff[(index * 3) + 2])
+    } else {
+        Color::RGB(255, 255, 255)
+    }
+}
@@ -0,0 +1,320 @@
+use std::fmt::Debug;
+use std::path::Path;
+
+use crate::game::{GameEvent, GameMode, GameSettings, Input, LockReset, TetrisGame};
+use crate::generator::Randomizer;
+use crate::rotation::RotationSystemKind;
+
+/// First line of every replay file, bumped when the format changes
+const REPLAY_HEADER: &str = "tetris-replay 1";
+
+/// Everything needed to play a game again: the game is deterministic so the seed,
+/// the settings and the inputs with the tick they were given at are enough
+///
+/// Saved as a text file, one `key value` setting per line then one `tick Input` line per input
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub settings: GameSettings,
+    /// Inputs in the order they were given, with the value of [`TetrisGame::ticks`] at that moment
+    pub inputs: Vec<(u64, Input)>
+}
+
+impl Replay {
+    /// Starts an empty recording of a game made with [`TetrisGame::with_settings`]
+    pub fn new(seed: u64, settings: GameSettings) -> Self {
+        Self {
+            seed,
+            settings,
+            inputs: Vec::new()
+        }
+    }
+
+    /// Remembers an input, call it right before giving the input to the game
+    pub fn record(&mut self, tick: u64, input: Input) {
+        self.inputs.push((tick, input));
+    }
+
+    /// The recorded game before any input
+    pub fn new_game(&self) -> TetrisGame {
+        TetrisGame::with_settings(self.seed, self.settings)
+    }
+
+    /// Tick of the last input, the game may go on after it
+    pub fn last_tick(&self) -> u64 {
+        self.inputs.last().map(|(tick, _)| *tick).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        std::fs::write(&path, self.to_text())
+            .map_err(|e| format!("can't write replay {}: {}", path.as_ref().display(), e))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("can't read replay {}: {}", path.as_ref().display(), e))?;
+        Self::from_text(&text)
+            .map_err(|e| format!("invalid replay {}: {}", path.as_ref().display(), e))
+    }
+
+    /// Writes the replay in the file format
+    pub fn to_text(&self) -> String {
+        let settings = &self.settings;
+        let lock_reset = match settings.lock_reset {
+            LockReset::MoveReset(resets) => format!("move {}", resets),
+            LockReset::StepReset => "step".to_string()
+        };
+
+        let mut text = format!(
+            "{}\nseed {}\nmode {:?}\nrandomizer {:?}\nrotation {:?}\nqueue_length {}\nlock_delay {}\nlock_reset {}\n\
+            start_level {}\ndas {}\narr {}\nsoft_drop_factor {}\nwidth {}\nheight {}\ninputs\n",
+            REPLAY_HEADER, self.seed, settings.mode, settings.randomizer, settings.rotation_system, settings.queue_length,
+            settings.lock_delay, lock_reset, settings.start_level, settings.das, settings.arr, settings.soft_drop_factor,
+            settings.width, settings.height
+        );
+        for (tick, input) in &self.inputs {
+            text.push_str(&format!("{} {:?}\n", tick, input));
+        }
+
+        text
+    }
+
+    /// Reads a replay written by [`Replay::to_text`], settings out of [`GameSettings::check`]'s ranges are refused
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate();
+        if lines.next().map(|(_, line)| line.trim()) != Some(REPLAY_HEADER) {
+            return Err(format!("first line should be \"{}\"", REPLAY_HEADER));
+        }
+
+        let mut replay = Replay::new(0, GameSettings::default());
+        let settings = &mut replay.settings;
+
+        // Settings until the inputs line
+        for (index, line) in lines.by_ref() {
+            let line = line.trim();
+            if line == "inputs" {
+                break;
+            }
+
+            let (key, value) = line.split_once(' ').ok_or(format!("line {}: expected a key and a value", index + 1))?;
+            let number = || value.parse::<usize>().map_err(|e| format!("line {}: {}", index + 1, e));
+            match key {
+                "seed" => replay.seed = value.parse().map_err(|e| format!("line {}: {}", index + 1, e))?,
+                "mode" => settings.mode = from_debug_name(&GameMode::ALL, value, index)?,
+                "randomizer" => settings.randomizer = from_debug_name(&Randomizer::ALL, value, index)?,
+                "rotation" => settings.rotation_system = from_debug_name(&RotationSystemKind::ALL, value, index)?,
+                "queue_length" => settings.queue_length = number()?,
+                "lock_delay" => settings.lock_delay = number()?,
+                "lock_reset" => settings.lock_reset = match value.split_once(' ') {
+                    Some(("move", resets)) => LockReset::MoveReset(resets.parse().map_err(|e| format!("line {}: {}", index + 1, e))?),
+                    _ if value == "step" => LockReset::StepReset,
+                    _ => return Err(format!("line {}: unknown lock reset \"{}\"", index + 1, value))
+                },
+                "start_level" => settings.start_level = number()?,
+                "das" => settings.das = number()?,
+                "arr" => settings.arr = number()?,
+                "soft_drop_factor" => settings.soft_drop_factor = number()?,
+                "width" => settings.width = number()?,
+                "height" => settings.height = number()?,
+                _ => return Err(format!("line {}: unknown setting \"{}\"", index + 1, key))
+            }
+        }
+
+        for (index, line) in lines {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (tick, input) = line.split_once(' ').ok_or(format!("line {}: expected a tick and an input", index + 1))?;
+            let tick = tick.parse().map_err(|e| format!("line {}: {}", index + 1, e))?;
+            if tick < replay.last_tick() {
+                return Err(format!("line {}: inputs are not in tick order", index + 1));
+            }
+            replay.record(tick, from_debug_name(&Input::ALL, input, index)?);
+        }
+
+        replay.settings.check()?;
+        Ok(replay)
+    }
+}
+
+/// Finds the value written as `name` with `{:?}`
+pub(crate) fn parse_debug_name<T: Copy + Debug>(values: &[T], name: &str) -> Option<T> {
+    values.iter().find(|value| format!("{:?}", value) == name).copied()
+}
+
+/// [`parse_debug_name`] with an error giving the line of the file
+fn from_debug_name<T: Copy + Debug>(values: &[T], name: &str, index: usize) -> Result<T, String> {
+    parse_debug_name(values, name).ok_or(format!("line {}: unknown value \"{}\"", index + 1, name))
+}
+
+/// Plays a [`Replay`] back one tick at a time
+///
+/// Seeking backwards replays the game from the start, nothing is stored besides the inputs
+pub struct ReplayPlayer<'a> {
+    replay: &'a Replay,
+    game: TetrisGame,
+    /// Index of the next input to give
+    next_input: usize
+}
+
+impl<'a> ReplayPlayer<'a> {
+    pub fn new(replay: &'a Replay) -> Self {
+        Self {
+            replay,
+            game: replay.new_game(),
+            next_input: 0
+        }
+    }
+
+    /// Game in its current state
+    pub fn game(&self) -> &TetrisGame {
+        &self.game
+    }
+
+    /// Takes every event of the game since the last call
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        self.game.drain_events()
+    }
+
+    /// Gives the inputs of the current tick then ticks the game, exactly like when it was recorded.
+    /// Does nothing once the game is over
+    pub fn step(&mut self) {
+        if self.game.is_gameover() {
+            return;
+        }
+
+        while let Some((tick, input)) = self.replay.inputs.get(self.next_input) {
+            if *tick > self.game.ticks() {
+                break;
+            }
+
+            self.game.handle_input(*input);
+            self.next_input += 1;
+        }
+
+        self.game.tick();
+    }
+
+    /// Goes to the given tick, or to the end of the game if it ended before
+    ///
+    /// Events of the skipped ticks are dropped
+    pub fn seek(&mut self, tick: u64) {
+        if tick < self.game.ticks() {
+            self.game = self.replay.new_game();
+            self.next_input = 0;
+        }
+
+        while self.game.ticks() < tick && !self.game.is_gameover() {
+            self.step();
+        }
+        self.game.drain_events();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replay with every setting away from its default
+    fn sample() -> Replay {
+        let settings = GameSettings {
+            mode: GameMode::Sprint,
+            randomizer: Randomizer::Nes,
+            queue_length: 3,
+            rotation_system: RotationSystemKind::Ars,
+            lock_delay: 20,
+            lock_reset: LockReset::StepReset,
+            start_level: 4,
+            das: 8,
+            arr: 1,
+            soft_drop_factor: 10,
+            width: 8,
+            height: 16
+        };
+
+        let mut replay = Replay::new(987654321, settings);
+        replay.record(0, Input::MoveLeftStart);
+        replay.record(12, Input::MoveLeftEnd);
+        replay.record(12, Input::RotateRight);
+        replay.record(40, Input::HardDrop);
+        replay.record(41, Input::Hold);
+        replay
+    }
+
+    /// Text of the sample replay with a line replaced
+    fn sample_text_with(from: &str, to: &str) -> String {
+        let text = sample().to_text();
+        assert!(text.contains(from));
+        text.replace(from, to)
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let replay = sample();
+        assert_eq!(Replay::from_text(&replay.to_text()), Ok(replay));
+
+        let mut move_reset = Replay::new(1, GameSettings::default());
+        move_reset.record(3, Input::SoftDropStart);
+        assert_eq!(Replay::from_text(&move_reset.to_text()), Ok(move_reset));
+    }
+
+    #[test]
+    fn bad_text_is_rejected() {
+        let bad = [
+            sample_text_with("tetris-replay 1", "tetris-replay 0"),
+            sample_text_with("mode Sprint", "mode Zen"),
+            sample_text_with("das 8", "das eight"),
+            sample_text_with("arr 1", "arr"),
+            sample_text_with("lock_reset step", "lock_reset sometimes"),
+            sample_text_with("width 8", "depth 8"),
+            sample_text_with("40 HardDrop", "40 Teleport"),
+            sample_text_with("40 HardDrop", "4 HardDrop"),
+            sample_text_with("40 HardDrop", "HardDrop"),
+            sample_text_with("width 8", "width 18446744073709551615"),
+            sample_text_with("height 16", "height 100000"),
+            sample_text_with("height 16", "height 2"),
+            sample_text_with("queue_length 3", "queue_length 1000000000"),
+            sample_text_with("start_level 4", "start_level 18446744073709551615"),
+            sample_text_with("start_level 4", "start_level 0"),
+            sample_text_with("lock_delay 20", "lock_delay 100000"),
+            sample_text_with("das 8", "das 100000"),
+            sample_text_with("arr 1", "arr 100000"),
+            sample_text_with("soft_drop_factor 10", "soft_drop_factor 0")
+        ];
+
+        for text in bad {
+            assert!(Replay::from_text(&text).is_err(), "accepted:\n{}", text);
+        }
+    }
+
+    #[test]
+    fn player_plays_the_recorded_game() {
+        let mut game = TetrisGame::new(5);
+        let mut replay = Replay::new(5, GameSettings::default());
+        for tick in 0..1500u64 {
+            if tick % 25 == 0 {
+                let input = Input::ALL[(tick / 25) as usize % Input::ALL.len()];
+                replay.record(game.ticks(), input);
+                game.handle_input(input);
+            }
+            game.tick();
+        }
+
+        let mut player = ReplayPlayer::new(&replay);
+        while player.game().ticks() < game.ticks() && !player.game().is_gameover() {
+            player.step();
+        }
+        assert_eq!(player.game().stats(), game.stats());
+        assert_eq!(player.game().board(), game.board());
+
+        // Seeking back plays the game again from the start
+        player.seek(600);
+        let board_at_600 = player.game().board().clone();
+        player.seek(1200);
+        player.seek(600);
+        assert_eq!(player.game().ticks(), 600);
+        assert_eq!(player.game().board(), &board_at_600);
+    }
+}
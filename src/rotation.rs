@@ -0,0 +1,315 @@
+use std::str::FromStr;
+
+use crate::board::Board;
+use crate::tetris::{Position, Tetris, TetrisType};
+
+/// Rotation state of a tetris, relative to how it spawns
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    /// Spawn orientation
+    #[default]
+    Spawn,
+    /// One clockwise turn from spawn
+    Right,
+    /// Two turns from spawn
+    Half,
+    /// One counter clockwise turn from spawn
+    Left
+}
+
+impl Rotation {
+    /// State after a clockwise turn
+    pub fn clockwise(self) -> Self {
+        match self {
+            Rotation::Spawn => Rotation::Right,
+            Rotation::Right => Rotation::Half,
+            Rotation::Half => Rotation::Left,
+            Rotation::Left => Rotation::Spawn
+        }
+    }
+
+    /// State after a counter clockwise turn
+    pub fn counter_clockwise(self) -> Self {
+        match self {
+            Rotation::Spawn => Rotation::Left,
+            Rotation::Right => Rotation::Spawn,
+            Rotation::Half => Rotation::Right,
+            Rotation::Left => Rotation::Half
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Rotation::Spawn => 0,
+            Rotation::Right => 1,
+            Rotation::Half => 2,
+            Rotation::Left => 3
+        }
+    }
+}
+
+/// Decides where a rotated tetris may go when its rotation is blocked
+///
+/// The game rotates the map around its center then tries every kick in order,
+/// the first one that doesn't hit anything wins
+pub trait RotationSystem: Send {
+    /// Shifts to try when rotating a tetris of this type from `from` to `to`, in order.
+    /// The first one is usually (0, 0), the rotation in place. It isn't for tetris whose map doesn't turn
+    /// around the center the system expects, like the SRS line and square which start with a correction
+    fn kicks(&self, tetris_type: &TetrisType, from: Rotation, to: Rotation) -> Vec<Position>;
+
+    /// Called when the rotation in place is blocked, returning false cancels the rotation without trying kicks
+    fn can_kick(&self, _rotated: &Tetris, _board: &Board) -> bool {
+        true
+    }
+}
+
+/// Rotation systems to choose from in the settings, written by name in replays
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RotationSystemKind {
+    /// Super Rotation System from the guideline
+    #[default]
+    Srs,
+    /// Rotation is cancelled if it hits something
+    NoKick,
+    /// Arika style kicks, one square right then left
+    Ars
+}
+
+impl RotationSystemKind {
+    /// Every built-in rotation system, in menu order
+    pub const ALL: [RotationSystemKind; 3] = [RotationSystemKind::Srs, RotationSystemKind::NoKick, RotationSystemKind::Ars];
+
+    pub fn system(&self) -> Box<dyn RotationSystem> {
+        match self {
+            RotationSystemKind::Srs => Box::new(Srs),
+            RotationSystemKind::NoKick => Box::new(NoKick),
+            RotationSystemKind::Ars => Box::new(Ars)
+        }
+    }
+}
+
+impl FromStr for RotationSystemKind {
+    type Err = String;
+
+    /// Parses the names used on the command line: srs, nokick and ars
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "srs" => Ok(RotationSystemKind::Srs),
+            "nokick" | "none" => Ok(RotationSystemKind::NoKick),
+            "ars" => Ok(RotationSystemKind::Ars),
+            _ => Err(format!("unknown rotation system \"{}\", expected srs, nokick or ars", name))
+        }
+    }
+}
+
+/// SRS offsets for J, L, S, T and Z, indexed by [`Rotation::index`]. Y goes up
+const JLSTZ_OFFSETS: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]
+];
+
+/// SRS offsets for the line, it doesn't rotate around a square so it has its own table
+const LINE_OFFSETS: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (-1, 0), (2, 0), (-1, 0), (2, 0)],
+    [(-1, 0), (0, 0), (0, 0), (0, 1), (0, -2)],
+    [(-1, 1), (1, 1), (-2, 1), (1, 0), (-2, 0)],
+    [(0, 1), (0, 1), (0, 1), (0, -1), (0, 2)]
+];
+
+/// SRS offsets for the square, only there to cancel the rotation around the map center
+const SQUARE_OFFSETS: [[(i32, i32); 1]; 4] = [
+    [(0, 0)],
+    [(0, -1)],
+    [(-1, -1)],
+    [(-1, 0)]
+];
+
+/// Super Rotation System
+///
+/// Uses the offset tables from the guideline, kicks are `offset[from] - offset[to]`.
+/// Custom tetris don't kick
+pub struct Srs;
+
+impl Srs {
+    fn offsets_to_kicks<const N: usize>(table: &[[(i32, i32); N]; 4], from: Rotation, to: Rotation) -> Vec<Position> {
+        table[from.index()]
+            .iter()
+            .zip(table[to.index()].iter())
+            // Tables have y going up, the board has y going down
+            .map(|(from, to)| Position { x: from.0 - to.0, y: -(from.1 - to.1) })
+            .collect()
+    }
+}
+
+impl RotationSystem for Srs {
+    fn kicks(&self, tetris_type: &TetrisType, from: Rotation, to: Rotation) -> Vec<Position> {
+        match tetris_type {
+            TetrisType::Line => Self::offsets_to_kicks(&LINE_OFFSETS, from, to),
+            TetrisType::Square => Self::offsets_to_kicks(&SQUARE_OFFSETS, from, to),
+            TetrisType::Garbage | TetrisType::Custom(_) => vec![Position { x: 0, y: 0 }],
+            _ => Self::offsets_to_kicks(&JLSTZ_OFFSETS, from, to)
+        }
+    }
+}
+
+/// Original behaviour, the rotation only happens if the rotated tetris fits in place
+pub struct NoKick;
+
+impl RotationSystem for NoKick {
+    fn kicks(&self, _tetris_type: &TetrisType, _from: Rotation, _to: Rotation) -> Vec<Position> {
+        vec![Position { x: 0, y: 0 }]
+    }
+}
+
+/// Arika Rotation System kicks
+///
+/// Tries in place, then one square right, then one square left. The line never kicks
+/// and L, J and T don't kick when the first blocked square is in their center column
+pub struct Ars;
+
+impl RotationSystem for Ars {
+    fn kicks(&self, tetris_type: &TetrisType, _from: Rotation, _to: Rotation) -> Vec<Position> {
+        match tetris_type {
+            TetrisType::Line => vec![Position { x: 0, y: 0 }],
+            _ => vec![Position { x: 0, y: 0 }, Position { x: 1, y: 0 }, Position { x: -1, y: 0 }]
+        }
+    }
+
+    fn can_kick(&self, rotated: &Tetris, board: &Board) -> bool {
+        if !matches!(rotated.tetris_type, TetrisType::LShapeLeft | TetrisType::LShapeRight | TetrisType::TShape) {
+            return true;
+        }
+
+        // Find the first blocked square, reading the map from the top left
+        for (yindex, row) in rotated.map.iter().enumerate() {
+            for (xindex, filled) in row.iter().enumerate() {
+                if *filled && board.is_blocked(xindex as i32 + rotated.position.x, yindex as i32 + rotated.position.y) {
+                    // Maps rotate around their center column
+                    return xindex != 2;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tetris::NATURAL_TETRIS;
+
+    use super::*;
+
+    /// Squares of the tetris on the board
+    fn squares(tetris: &Tetris) -> Vec<(i32, i32)> {
+        let mut squares = Vec::new();
+        for (yindex, row) in tetris.map.iter().enumerate() {
+            for (xindex, filled) in row.iter().enumerate() {
+                if *filled {
+                    squares.push((xindex as i32 + tetris.position.x, yindex as i32 + tetris.position.y));
+                }
+            }
+        }
+        squares.sort();
+        squares
+    }
+
+    /// Rotates right and applies the first kick, like on an empty board
+    fn rotate_right(tetris: &Tetris) -> Tetris {
+        let mut rotated = *tetris;
+        rotated.rotate_right();
+        let kick = Srs.kicks(&rotated.tetris_type, tetris.rotation, rotated.rotation)[0];
+        rotated.position.x += kick.x;
+        rotated.position.y += kick.y;
+        rotated
+    }
+
+    fn positions(kicks: &[(i32, i32)]) -> Vec<Position> {
+        kicks.iter().map(|&(x, y)| Position { x, y }).collect()
+    }
+
+    #[test]
+    fn srs_jlstz_kicks_follow_the_guideline() {
+        // Guideline tables with y going down
+        let expected = [
+            (Rotation::Spawn, Rotation::Right, [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]),
+            (Rotation::Right, Rotation::Spawn, [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]),
+            (Rotation::Right, Rotation::Half, [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]),
+            (Rotation::Half, Rotation::Left, [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]),
+            (Rotation::Left, Rotation::Spawn, [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]),
+            (Rotation::Spawn, Rotation::Left, [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)])
+        ];
+
+        for tetris_type in [TetrisType::TShape, TetrisType::LShapeLeft, TetrisType::LShapeRight, TetrisType::ZShapeLeft, TetrisType::ZShapeRight] {
+            for (from, to, kicks) in expected {
+                assert_eq!(Srs.kicks(&tetris_type, from, to), positions(&kicks), "{:?} {:?} to {:?}", tetris_type, from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn srs_line_kicks_follow_the_guideline() {
+        // Guideline tables with y going down, the first kick of ours moves the line to the guideline rotation center
+        let expected = [
+            (Rotation::Spawn, Rotation::Right, [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)]),
+            (Rotation::Right, Rotation::Spawn, [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)]),
+            (Rotation::Right, Rotation::Half, [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)]),
+            (Rotation::Half, Rotation::Left, [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)]),
+            (Rotation::Left, Rotation::Spawn, [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)]),
+            (Rotation::Spawn, Rotation::Left, [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)])
+        ];
+
+        for (from, to, kicks) in expected {
+            let ours = Srs.kicks(&TetrisType::Line, from, to);
+            let relative: Vec<Position> = ours.iter().map(|kick| Position { x: kick.x - ours[0].x, y: kick.y - ours[0].y }).collect();
+            assert_eq!(relative, positions(&kicks), "{:?} to {:?}", from, to);
+        }
+    }
+
+    #[test]
+    fn srs_line_rotates_around_the_guideline_center() {
+        let spawn = Tetris::new(TetrisType::Line);
+        let right = rotate_right(&spawn);
+
+        // Flat in the second row of its 4x4 box, then standing in the third column
+        let box_x = spawn.position.x + 1;
+        let box_y = spawn.position.y + 1;
+        assert_eq!(squares(&spawn), (0..4).map(|x| (box_x + x, box_y + 1)).collect::<Vec<_>>());
+        assert_eq!(squares(&right), (0..4).map(|y| (box_x + 2, box_y + y)).collect::<Vec<_>>());
+
+        let back = rotate_right(&rotate_right(&rotate_right(&right)));
+        assert_eq!(squares(&back), squares(&spawn));
+    }
+
+    #[test]
+    fn srs_square_never_moves() {
+        let mut square = Tetris::new(TetrisType::Square);
+        let spawn = squares(&square);
+
+        for _ in 0..4 {
+            square = rotate_right(&square);
+            assert_eq!(squares(&square), spawn, "{:?}", square.rotation);
+            assert_eq!(Srs.kicks(&TetrisType::Square, Rotation::Spawn, square.rotation).len(), 1);
+        }
+    }
+
+    #[test]
+    fn other_systems_start_in_place() {
+        let in_place = Position { x: 0, y: 0 };
+        for tetris_type in NATURAL_TETRIS {
+            assert_eq!(NoKick.kicks(&tetris_type, Rotation::Spawn, Rotation::Right), vec![in_place]);
+            assert_eq!(Ars.kicks(&tetris_type, Rotation::Spawn, Rotation::Right)[0], in_place);
+        }
+        assert_eq!(Ars.kicks(&TetrisType::Line, Rotation::Spawn, Rotation::Right).len(), 1);
+    }
+
+    #[test]
+    fn names_parse() {
+        assert_eq!("SRS".parse(), Ok(RotationSystemKind::Srs));
+        assert_eq!("none".parse(), Ok(RotationSystemKind::NoKick));
+        assert!("tgm".parse::<RotationSystemKind>().is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::game::TICKS_PER_SECOND;
+
+/// Lines to clear to go up a level
+pub const LINES_PER_LEVEL: usize = 10;
+/// Last level, gravity and the points multiplier stop growing there
+pub const MAX_GRAVITY_LEVEL: usize = 20;
+
+/// Points given by a soft drop, per row
+pub const SOFT_DROP_POINTS: usize = 1;
+/// Points given by a hard drop, per row
+pub const HARD_DROP_POINTS: usize = 2;
+/// Points given per combo step, multiplied by the level
+pub const COMBO_POINTS: usize = 50;
+
+/// Extra garbage lines sent by a combo in versus, by combo count
+const COMBO_GARBAGE: [usize; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+
+/// Points for clearing lines at level 1, following the guideline
+pub fn line_clear_points(lines: usize, tspin: bool) -> usize {
+    match (tspin, lines) {
+        (false, 0) => 0,
+        (false, 1) => 100,
+        (false, 2) => 300,
+        (false, 3) => 500,
+        (false, _) => 800,
+        (true, 0) => 400,
+        (true, 1) => 800,
+        (true, 2) => 1200,
+        (true, _) => 1600
+    }
+}
+
+/// True if the clear keeps a back-to-back chain going (tetris or T-spin with lines)
+pub fn is_difficult_clear(lines: usize, tspin: bool) -> bool {
+    lines >= 4 || (tspin && lines > 0)
+}
+
+/// Garbage lines a clear sends in versus, following the guideline attack table
+///
+/// 0, 1, 2 and 4 lines for 1 to 4 lines, twice the lines for a T-spin,
+/// one more when back-to-back applies and more for long combos
+pub fn garbage_lines(lines: usize, tspin: bool, back_to_back: bool, combo: usize) -> usize {
+    if lines == 0 {
+        return 0;
+    }
+
+    let base = match (tspin, lines) {
+        (true, _) => lines * 2,
+        (false, 1) => 0,
+        (false, 2) => 1,
+        (false, 3) => 2,
+        (false, _) => 4
+    };
+
+    base + back_to_back as usize + COMBO_GARBAGE[combo.min(COMBO_GARBAGE.len() - 1)]
+}
+
+/// Level reached after clearing `lines` when starting at `start_level`, never above [`MAX_GRAVITY_LEVEL`]
+pub fn level_for_lines(start_level: usize, lines: usize) -> usize {
+    (start_level.max(1) + lines / LINES_PER_LEVEL).min(MAX_GRAVITY_LEVEL)
+}
+
+/// Number of ticks a tetris takes to fall one row at this level
+///
+/// Uses the guideline curve `(0.8 - (level - 1) * 0.007) ^ (level - 1)` seconds per row,
+/// capped at [`MAX_GRAVITY_LEVEL`] and never faster than one row per tick
+pub fn gravity_ticks(level: usize) -> usize {
+    let level = level.clamp(1, MAX_GRAVITY_LEVEL) as i32;
+    let seconds = (0.8 - (level - 1) as f64 * 0.007).powi(level - 1);
+
+    ((seconds * TICKS_PER_SECOND as f64).round() as usize).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_clears_follow_the_guideline() {
+        let points: Vec<usize> = (0..=4).map(|lines| line_clear_points(lines, false)).collect();
+        assert_eq!(points, [0, 100, 300, 500, 800]);
+
+        let tspin_points: Vec<usize> = (0..=3).map(|lines| line_clear_points(lines, true)).collect();
+        assert_eq!(tspin_points, [400, 800, 1200, 1600]);
+    }
+
+    #[test]
+    fn only_tetris_and_tspins_keep_back_to_back() {
+        assert!(is_difficult_clear(4, false));
+        assert!(is_difficult_clear(1, true));
+        assert!(!is_difficult_clear(3, false));
+        assert!(!is_difficult_clear(0, true));
+    }
+
+    #[test]
+    fn garbage_follows_the_attack_table() {
+        let attacks: Vec<usize> = (0..=4).map(|lines| garbage_lines(lines, false, false, 0)).collect();
+        assert_eq!(attacks, [0, 0, 1, 2, 4]);
+        assert_eq!(garbage_lines(2, true, false, 0), 4);
+        assert_eq!(garbage_lines(4, false, true, 0), 5);
+        assert_eq!(garbage_lines(1, false, false, 2), 1);
+        // Long combos stop growing at the end of the table
+        assert_eq!(garbage_lines(1, false, false, 100), garbage_lines(1, false, false, COMBO_GARBAGE.len() - 1));
+    }
+
+    #[test]
+    fn level_goes_up_every_10_lines_until_the_last_one() {
+        assert_eq!(level_for_lines(1, 0), 1);
+        assert_eq!(level_for_lines(0, 9), 1);
+        assert_eq!(level_for_lines(1, 10), 2);
+        assert_eq!(level_for_lines(5, 35), 8);
+        assert_eq!(level_for_lines(1, 100_000), MAX_GRAVITY_LEVEL);
+        assert_eq!(level_for_lines(MAX_GRAVITY_LEVEL + 10, 0), MAX_GRAVITY_LEVEL);
+    }
+
+    #[test]
+    fn gravity_gets_faster_until_the_last_level() {
+        assert_eq!(gravity_ticks(1), TICKS_PER_SECOND);
+        for level in 2..=MAX_GRAVITY_LEVEL {
+            assert!(gravity_ticks(level) <= gravity_ticks(level - 1), "level {}", level);
+        }
+        assert_eq!(gravity_ticks(MAX_GRAVITY_LEVEL), 1);
+        assert_eq!(gravity_ticks(1000), gravity_ticks(MAX_GRAVITY_LEVEL));
+    }
+}
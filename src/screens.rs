@@ -0,0 +1,648 @@
+use std::net::TcpListener;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use sdl2::controller::Button;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::event::WindowEvent;
+use tetris_sdl_rs::scoring;
+use tetris_sdl_rs::{
+    Broadcaster, GameMode, GameSettings, Input, LockReset, NetRole, NetSession, Replay, ReplayPlayer, RotationSystemKind, Randomizer, Versus, VersusResult,
+    MAX_ARR, MAX_DAS, MAX_LOCK_DELAY, MAX_QUEUE_LENGTH, MAX_SOFT_DROP_FACTOR, PLAYERS, SPRINT_LINES, TICKS_PER_SECOND
+};
+
+use crate::controls::{Action, Binding, Controls};
+use crate::highscores::{HighScore, HighScores, ENTRIES_PER_MODE, MAX_NAME_LENGTH};
+use crate::menu::{Menu, MenuAction};
+use crate::pads::{self, EventPump};
+use crate::renderer::{format_time, Renderer};
+use crate::{State, FPS};
+
+/// Fastest replay speed, in ticks per frame
+const MAX_REPLAY_SPEED: usize = 16;
+/// Ticks skipped by a seek in a replay
+const SEEK_TICKS: u64 = 5 * TICKS_PER_SECOND as u64;
+
+/// Shows a page until the player does something with its menu
+///
+/// Breaks if the window was closed
+fn choose(renderer: &mut Renderer, event_pump: &mut EventPump, title: &str, lines: &[String], menu: &mut Menu) -> Result<ControlFlow<(), MenuAction>, String> {
+    loop {
+        for event in event_pump.poll_iter() {
+            match menu.handle_event(&event) {
+                Some(MenuAction::Quit) => return Ok(ControlFlow::Break(())),
+                Some(action) => return Ok(ControlFlow::Continue(action)),
+                None => {}
+            }
+        }
+
+        renderer.draw_page(title, lines, Some(menu))?;
+
+        std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));
+    }
+}
+
+/// Title screen, first thing shown at startup
+pub fn title(renderer: &mut Renderer, event_pump: &mut EventPump) -> Result<ControlFlow<(), State>, String> {
+    let mut menu = Menu::new(&["Play", "Settings", "Controls", "High scores", "Credits", "Quit"]);
+
+    loop {
+        let next_state = match choose(renderer, event_pump, "TETRIS", &[], &mut menu)? {
+            ControlFlow::Break(()) | ControlFlow::Continue(MenuAction::Select(5) | MenuAction::Back) => return Ok(ControlFlow::Break(())),
+            ControlFlow::Continue(MenuAction::Select(0)) => State::ModeSelect,
+            ControlFlow::Continue(MenuAction::Select(1)) => State::Settings,
+            ControlFlow::Continue(MenuAction::Select(2)) => State::Controls,
+            ControlFlow::Continue(MenuAction::Select(3)) => State::HighScores,
+            ControlFlow::Continue(MenuAction::Select(4)) => State::Credits,
+            ControlFlow::Continue(_) => continue
+        };
+
+        return Ok(ControlFlow::Continue(next_state));
+    }
+}
+
+/// Lets the player pick the game mode before playing
+pub fn mode_select(renderer: &mut Renderer, event_pump: &mut EventPump) -> Result<ControlFlow<(), State>, String> {
+    let sprint = format!("Sprint ({} lines)", SPRINT_LINES);
+    let mut menu = Menu::new(&["Marathon", &sprint, "Ultra (2 minutes)", "Versus (2 players)", "Back"]);
+
+    loop {
+        let next_state = match choose(renderer, event_pump, "MODE", &[], &mut menu)? {
+            ControlFlow::Break(()) => return Ok(ControlFlow::Break(())),
+            ControlFlow::Continue(MenuAction::Select(index)) if index < GameMode::ALL.len() => State::Tetris(GameMode::ALL[index]),
+            ControlFlow::Continue(MenuAction::Select(index)) if index == GameMode::ALL.len() => State::Versus,
+            ControlFlow::Continue(MenuAction::Select(_) | MenuAction::Back) => State::Title,
+            ControlFlow::Continue(_) => continue
+        };
+
+        return Ok(ControlFlow::Continue(next_state));
+    }
+}
+
+/// Best games of each mode, Left and Right switch mode
+pub fn high_scores(renderer: &mut Renderer, event_pump: &mut EventPump, high_scores: &HighScores) -> Result<ControlFlow<()>, String> {
+    let mut menu = Menu::new(&["Back"]);
+    let mut mode_index = 0;
+
+    loop {
+        let mode = GameMode::ALL[mode_index];
+        let mut lines = vec![format!("< {:?} >", mode)];
+        if let Some(error) = &high_scores.error {
+            lines.push(error.clone());
+        }
+        for (rank, entry) in high_scores.ranked(mode).iter().take(ENTRIES_PER_MODE).enumerate() {
+            lines.extend(format_entry(rank, entry));
+        }
+
+        match choose(renderer, event_pump, "HIGH SCORES", &lines, &mut menu)? {
+            ControlFlow::Break(()) => return Ok(ControlFlow::Break(())),
+            ControlFlow::Continue(MenuAction::Adjust(_, delta)) => {
+                mode_index = (mode_index as i32 + delta).rem_euclid(GameMode::ALL.len() as i32) as usize;
+            },
+            ControlFlow::Continue(_) => return Ok(ControlFlow::Continue(()))
+        }
+    }
+}
+
+/// Two lines of the high scores: the result, then the details
+fn format_entry(rank: usize, entry: &HighScore) -> [String; 2] {
+    let stats = &entry.stats;
+    let result = if entry.mode == GameMode::Sprint {
+        format!("{}. {}  {}  ({} pieces)", rank + 1, entry.name, format_time(stats.ticks), stats.pieces)
+    } else {
+        format!("{}. {}  {}  ({} lines)", rank + 1, entry.name, stats.score, stats.lines)
+    };
+    let details = format!("{}  level {}  {}  seed {}", entry.date_text(), stats.level, format_time(stats.ticks), stats.seed);
+
+    [result, details]
+}
+
+/// Asks the name of the player for a new high score under the game results
+///
+/// Continues with true if the name was confirmed, false if the player skipped
+pub fn name_entry(renderer: &mut Renderer, event_pump: &mut EventPump, title: &str, results: &[String], name: &mut String) -> Result<ControlFlow<(), bool>, String> {
+    loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => return Ok(ControlFlow::Break(())),
+                Event::TextInput { text, .. } => {
+                    for c in text.chars().filter(|c| !c.is_control()) {
+                        if name.chars().count() < MAX_NAME_LENGTH {
+                            name.push(c);
+                        }
+                    }
+                },
+                Event::KeyDown { keycode: Some(Keycode::Backspace), .. } => {
+                    name.pop();
+                },
+                Event::KeyDown { keycode: Some(Keycode::Return | Keycode::KpEnter), repeat: false, .. } |
+                Event::ControllerButtonDown { button: Button::A, .. } => {
+                    return Ok(ControlFlow::Continue(true));
+                },
+                Event::KeyDown { keycode: Some(Keycode::Escape), repeat: false, .. } |
+                Event::ControllerButtonDown { button: Button::B, .. } => {
+                    return Ok(ControlFlow::Continue(false));
+                },
+                _ => {}
+            }
+        }
+
+        let mut lines = results.to_vec();
+        lines.extend([
+            String::new(),
+            "NEW HIGH SCORE".to_string(),
+            format!("Name: {}_", name),
+            "Enter to save, Escape to skip".to_string()
+        ]);
+        renderer.draw_page(title, &lines, None)?;
+
+        std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));
+    }
+}
+
+/// Who made the game
+pub fn credits(renderer: &mut Renderer, event_pump: &mut EventPump) -> Result<ControlFlow<()>, String> {
+    let lines = [
+        "Made by NormalFall".to_string(),
+        "Written in Rust with SDL2".to_string(),
+        "Font: Roboto".to_string()
+    ];
+    let mut menu = Menu::new(&["Back"]);
+
+    loop {
+        match choose(renderer, event_pump, "CREDITS", &lines, &mut menu)? {
+            ControlFlow::Break(()) => return Ok(ControlFlow::Break(())),
+            ControlFlow::Continue(MenuAction::Select(_) | MenuAction::Back) => return Ok(ControlFlow::Continue(())),
+            ControlFlow::Continue(_) => {}
+        }
+    }
+}
+
+/// Lets the player change the settings used by the next games
+///
+/// Breaks if the window was closed
+pub fn settings(renderer: &mut Renderer, event_pump: &mut EventPump, settings: &mut GameSettings) -> Result<ControlFlow<()>, String> {
+    let mut menu = Menu::new(&[]);
+
+    loop {
+        menu.items = settings_items(settings);
+        let back_index = menu.items.len() - 1;
+
+        match choose(renderer, event_pump, "SETTINGS", &[], &mut menu)? {
+            ControlFlow::Break(()) => return Ok(ControlFlow::Break(())),
+            ControlFlow::Continue(MenuAction::Back) => return Ok(ControlFlow::Continue(())),
+            ControlFlow::Continue(MenuAction::Select(index)) if index == back_index => return Ok(ControlFlow::Continue(())),
+            ControlFlow::Continue(MenuAction::Select(index)) => adjust_setting(settings, index, 1),
+            ControlFlow::Continue(MenuAction::Adjust(index, delta)) => adjust_setting(settings, index, delta),
+            ControlFlow::Continue(MenuAction::Quit) => {}
+        }
+    }
+}
+
+/// Menu lines of the settings screen, the last one is always Back
+fn settings_items(settings: &GameSettings) -> Vec<String> {
+    let lock_reset = match settings.lock_reset {
+        LockReset::MoveReset(_) => "Move",
+        LockReset::StepReset => "Step"
+    };
+
+    vec![
+        format!("Next pieces: {}", settings.queue_length),
+        format!("Randomizer: {:?}", settings.randomizer),
+        format!("Rotation: {:?}", settings.rotation_system),
+        format!("Start level: {}", settings.start_level),
+        format!("Lock delay: {} ms", settings.lock_delay * 1000 / TICKS_PER_SECOND),
+        format!("Lock reset: {}", lock_reset),
+        format!("DAS: {} ms", settings.das * 1000 / TICKS_PER_SECOND),
+        format!("ARR: {} ms", settings.arr * 1000 / TICKS_PER_SECOND),
+        format!("Soft drop speed: {}x", settings.soft_drop_factor),
+        "Back".to_string()
+    ]
+}
+
+/// Changes the setting at the menu index by one step
+fn adjust_setting(settings: &mut GameSettings, index: usize, delta: i32) {
+    match index {
+        0 => settings.queue_length = step(settings.queue_length, delta, 1, MAX_QUEUE_LENGTH),
+        1 => settings.randomizer = cycle(&Randomizer::ALL, settings.randomizer, delta),
+        2 => settings.rotation_system = cycle(&RotationSystemKind::ALL, settings.rotation_system, delta),
+        3 => settings.start_level = step(settings.start_level, delta, 1, scoring::MAX_GRAVITY_LEVEL),
+        4 => settings.lock_delay = step(settings.lock_delay, delta * 3, 0, MAX_LOCK_DELAY),
+        5 => settings.lock_reset = match settings.lock_reset {
+            LockReset::MoveReset(_) => LockReset::StepReset,
+            LockReset::StepReset => GameSettings::default().lock_reset
+        },
+        6 => settings.das = step(settings.das, delta, 0, MAX_DAS),
+        7 => settings.arr = step(settings.arr, delta, 0, MAX_ARR),
+        8 => settings.soft_drop_factor = step(settings.soft_drop_factor, delta, 1, MAX_SOFT_DROP_FACTOR),
+        _ => {}
+    }
+}
+
+/// Lets the player rebind the controls, every change is written to the config file right away
+///
+/// Breaks if the window was closed
+pub fn controls(renderer: &mut Renderer, event_pump: &mut EventPump, controls: &mut Controls) -> Result<ControlFlow<()>, String> {
+    let mut menu = Menu::new(&[]);
+
+    loop {
+        menu.items = Action::ALL
+            .iter()
+            .map(|action| format!("{}: {}", action.label(), controls.describe(*action)))
+            .chain([
+                format!("Stick dead zone: {:.0}%", controls.dead_zone() * 100.0),
+                "Reset to defaults".to_string(),
+                "Back".to_string()
+            ])
+            .collect();
+        let dead_zone_index = Action::ALL.len();
+        let mut lines: Vec<String> = controls.error.iter().cloned().collect();
+        lines.push("Select an action to add a key, button or stick".to_string());
+
+        match choose(renderer, event_pump, "CONTROLS", &lines, &mut menu)? {
+            ControlFlow::Break(()) => return Ok(ControlFlow::Break(())),
+            ControlFlow::Continue(MenuAction::Select(index)) if index < Action::ALL.len() => {
+                if wait_for_binding(renderer, event_pump, controls, Action::ALL[index])?.is_break() {
+                    return Ok(ControlFlow::Break(()));
+                }
+            },
+            ControlFlow::Continue(MenuAction::Select(index)) if index == dead_zone_index => {
+                controls.adjust_dead_zone(1);
+                controls.save();
+            },
+            ControlFlow::Continue(MenuAction::Adjust(index, delta)) if index == dead_zone_index => {
+                controls.adjust_dead_zone(delta);
+                controls.save();
+            },
+            ControlFlow::Continue(MenuAction::Select(index)) if index == dead_zone_index + 1 => {
+                controls.reset();
+                controls.save();
+            },
+            ControlFlow::Continue(MenuAction::Select(_) | MenuAction::Back) => return Ok(ControlFlow::Continue(())),
+            ControlFlow::Continue(_) => {}
+        }
+    }
+}
+
+/// Binds the next key, button or stick pushed to the action. Escape cancels and Backspace removes every binding
+fn wait_for_binding(renderer: &mut Renderer, event_pump: &mut EventPump, controls: &mut Controls, action: Action) -> Result<ControlFlow<()>, String> {
+    let lines = [
+        format!("Press a key, button or stick for {}", action.label()),
+        "Escape to cancel, Backspace to clear".to_string()
+    ];
+    renderer.draw_page("CONTROLS", &lines, None)?;
+
+    loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => return Ok(ControlFlow::Break(())),
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => return Ok(ControlFlow::Continue(())),
+                Event::KeyDown { keycode: Some(Keycode::Backspace), .. } => {
+                    controls.clear(action);
+                    controls.save();
+                    return Ok(ControlFlow::Continue(()));
+                },
+                _ => {
+                    if let Some(binding) = Binding::pressed(&event) {
+                        controls.bind(action, binding);
+                        controls.save();
+                        return Ok(ControlFlow::Continue(()));
+                    }
+                }
+            }
+        }
+
+        std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));
+    }
+}
+
+/// Adds delta to value, staying between min and max
+fn step(value: usize, delta: i32, min: usize, max: usize) -> usize {
+    (value as i32 + delta).clamp(min as i32, max as i32) as usize
+}
+
+/// Moves to the next or previous value of the list, wrapping around
+fn cycle<T: Copy + PartialEq>(all: &[T], current: T, delta: i32) -> T {
+    let index = all.iter().position(|value| *value == current).unwrap_or(0) as i32;
+    all[(index + delta).rem_euclid(all.len() as i32) as usize]
+}
+
+/// Watches a recorded game until the window is closed or Escape is pressed
+///
+/// Space pauses, Up and Down change the speed, Left and Right seek 5 seconds and Home goes back to the start
+pub fn replay(renderer: &mut Renderer, event_pump: &mut EventPump, replay: &Replay) -> Result<(), String> {
+    let mut player = ReplayPlayer::new(replay);
+    let mut paused = false;
+    let mut speed = 1;
+
+    loop {
+        for event in event_pump.poll_iter() {
+            let ticks = player.game().ticks();
+            match event {
+                Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => return Ok(()),
+                Event::KeyDown { keycode: Some(Keycode::Space | Keycode::P), repeat: false, .. } => paused = !paused,
+                Event::KeyDown { keycode: Some(Keycode::Up), .. } => speed = (speed * 2).min(MAX_REPLAY_SPEED),
+                Event::KeyDown { keycode: Some(Keycode::Down), .. } => speed = (speed / 2).max(1),
+                Event::KeyDown { keycode: Some(Keycode::Left), .. } => player.seek(ticks.saturating_sub(SEEK_TICKS)),
+                Event::KeyDown { keycode: Some(Keycode::Right), .. } => player.seek(ticks + SEEK_TICKS),
+                Event::KeyDown { keycode: Some(Keycode::Home), .. } => player.seek(0),
+                _ => {}
+            }
+        }
+
+        if !paused {
+            for _ in 0..speed {
+                player.step();
+            }
+        }
+        player.drain_events();
+
+        let status = if player.game().is_gameover() {
+            "REPLAY END".to_string()
+        } else if paused {
+            "REPLAY PAUSED".to_string()
+        } else {
+            format!("REPLAY x{}", speed)
+        };
+        renderer.draw_with_status(player.game(), &status)?;
+
+        std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));
+    }
+}
+
+/// Split screen match, player 1 on the left half of the keyboard, player 2 on the right half.
+/// Controllers go to the players in the order they were plugged in
+///
+/// Returns the next state, Versus again for a restart or a rematch
+pub fn versus(renderer: &mut Renderer, event_pump: &mut EventPump, controls: &Controls, broadcaster: &mut Option<Broadcaster>, seed: u64, settings: GameSettings) -> Result<ControlFlow<(), State>, String> {
+    let mut versus = Versus::new(seed, settings);
+    let mut player_controls: Vec<Controls> = (0..PLAYERS).map(|player| controls.for_player(player)).collect();
+    renderer.draw_versus(&versus, None)?;
+
+    // Some while paused, the games don't tick
+    let mut pause_menu: Option<Menu> = None;
+
+    while versus.result().is_none() {
+        if let Some(menu) = pause_menu.as_mut() {
+            let mut action = None;
+            for event in event_pump.poll_iter() {
+                action = menu.handle_event(&event).or(action);
+            }
+
+            match action {
+                Some(MenuAction::Quit) => return Ok(ControlFlow::Break(())),
+                Some(MenuAction::Back | MenuAction::Select(0)) => {
+                    pause_menu = None;
+                    renderer.draw_versus(&versus, None)?;
+                },
+                Some(MenuAction::Select(1)) => return Ok(ControlFlow::Continue(State::Versus)),
+                Some(MenuAction::Select(2)) => return Ok(ControlFlow::Continue(State::Title)),
+                _ => {}
+            }
+
+            if let Some(menu) = &pause_menu {
+                renderer.draw_menu("PAUSED", menu)?;
+            }
+
+            std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));
+            continue;
+        }
+
+        for event in event_pump.poll_iter() {
+            let pause = match event {
+                Event::Quit { .. } => return Ok(ControlFlow::Break(())),
+                Event::Window { win_event: WindowEvent::FocusLost, .. } => true,
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    for controls in player_controls.iter_mut() {
+                        controls.forget_pad(which);
+                    }
+                    true
+                },
+                _ => {
+                    // Keys go to both players, their halves don't overlap. Controller events only go to their owner
+                    let owner = pads::controller_of(&event).map(|which| event_pump.player_of(which));
+                    let mut pause = false;
+                    for (player, controls) in player_controls.iter_mut().enumerate() {
+                        if owner.is_some_and(|owner| owner != Some(player)) {
+                            continue;
+                        }
+
+                        for (action, pressed) in controls.changes(&event) {
+                            if action == Action::Pause && pressed {
+                                pause = true;
+                            } else if let Some(input) = action.input(pressed) {
+                                versus.handle_input(player, input);
+                            }
+                        }
+                    }
+                    pause
+                }
+            };
+
+            if pause {
+                for player in 0..PLAYERS {
+                    for input in Input::RELEASES {
+                        versus.handle_input(player, input);
+                    }
+                }
+                pause_menu = Some(Menu::new(&["Resume", "Restart", "Quit to title"]));
+            }
+        }
+
+        versus.tick();
+        for player in 0..PLAYERS {
+            versus.drain_events(player);
+            if let Some(broadcaster) = broadcaster {
+                broadcaster.broadcast(player, versus.game(player));
+            }
+        }
+        renderer.draw_versus(&versus, None)?;
+
+        std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));
+    }
+
+    versus_result(renderer, event_pump, &versus)
+}
+
+/// Who won the match and how both players did, with a rematch
+fn versus_result(renderer: &mut Renderer, event_pump: &mut EventPump, versus: &Versus) -> Result<ControlFlow<(), State>, String> {
+    let title = match versus.result() {
+        Some(VersusResult::Winner(player)) => format!("PLAYER {} WINS", player + 1),
+        _ => "DRAW".to_string()
+    };
+    let lines = versus_lines(versus);
+    let mut menu = Menu::new(&["Rematch", "Quit to title"]);
+
+    loop {
+        let next_state = match choose(renderer, event_pump, &title, &lines, &mut menu)? {
+            ControlFlow::Break(()) => return Ok(ControlFlow::Break(())),
+            ControlFlow::Continue(MenuAction::Select(0)) => State::Versus,
+            ControlFlow::Continue(MenuAction::Select(_) | MenuAction::Back) => State::Title,
+            ControlFlow::Continue(_) => continue
+        };
+
+        return Ok(ControlFlow::Continue(next_state));
+    }
+}
+
+/// How each player did in a versus match, one line per player
+fn versus_lines(versus: &Versus) -> Vec<String> {
+    (0..PLAYERS)
+        .map(|player| {
+            let game = versus.game(player);
+            format!(
+                "Player {}: {} lines, {} garbage sent, {}",
+                player + 1, game.lines(), versus.garbage_sent(player), format_time(game.ticks())
+            )
+        })
+        .collect()
+}
+
+/// Versus match against a player on another computer, see [`NetSession`]
+///
+/// The local player uses the normal controls. Nothing can be paused, the pause key leaves the match
+pub fn net_versus(renderer: &mut Renderer, event_pump: &mut EventPump, controls: &mut Controls, broadcaster: &mut Option<Broadcaster>, role: &NetRole, seed: u64, settings: GameSettings) -> Result<(), String> {
+    let connected = match role {
+        NetRole::Host(port) => host(renderer, event_pump, *port, seed, settings)?,
+        NetRole::Join(address) => {
+            renderer.draw_page("NETWORK", &[format!("Connecting to {}", address)], None)?;
+            NetSession::join(address).map(Some)
+        }
+    };
+    let mut session = match connected {
+        Ok(Some(session)) => session,
+        Ok(None) => return Ok(()),
+        Err(e) => return notice(renderer, event_pump, "NETWORK ERROR", &[e])
+    };
+    let player = session.local_player();
+
+    // Frames spent waiting for the other side, the status only shows up on real lag
+    let mut waiting_frames = 0;
+
+    while session.versus().result().is_none() {
+        let mut leave = false;
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => leave = true,
+                Event::Window { win_event: WindowEvent::FocusLost, .. } | Event::ControllerDeviceRemoved { .. } => {
+                    if let Event::ControllerDeviceRemoved { which, .. } = event {
+                        controls.forget_pad(which);
+                    }
+                    // The match goes on without the keys being held
+                    for input in Input::RELEASES {
+                        session.give_input(input);
+                    }
+                },
+                _ => {
+                    for (action, pressed) in controls.changes(&event) {
+                        if action == Action::Pause && pressed {
+                            leave = true;
+                        } else if let Some(input) = action.input(pressed) {
+                            session.give_input(input);
+                        }
+                    }
+                }
+            }
+        }
+
+        if leave {
+            session.leave();
+            return Ok(());
+        }
+
+        let played = match session.update() {
+            Ok(played) => played,
+            Err(e) => {
+                let mut lines = vec![e];
+                lines.extend(versus_lines(session.versus()));
+                return notice(renderer, event_pump, "CONNECTION LOST", &lines);
+            }
+        };
+        waiting_frames = if played { 0 } else { waiting_frames + 1 };
+        for index in 0..PLAYERS {
+            session.drain_events(index);
+            // Nothing new to show while waiting for the other player
+            if played && let Some(broadcaster) = broadcaster {
+                broadcaster.broadcast(index, session.versus().game(index));
+            }
+        }
+
+        let status = format!("You are player {}", player + 1);
+        let waiting = "Waiting for the other player...";
+        renderer.draw_versus(session.versus(), Some(if waiting_frames > FPS { waiting } else { &status }))?;
+
+        std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));
+    }
+
+    // Lets the other side play the last ticks before closing
+    session.leave();
+
+    let title = match session.versus().result() {
+        Some(VersusResult::Winner(winner)) if winner == player => "YOU WIN",
+        Some(VersusResult::Winner(_)) => "YOU LOSE",
+        _ => "DRAW"
+    };
+    notice(renderer, event_pump, title, &versus_lines(session.versus()))
+}
+
+/// Waits for a player on the port, Escape gives up
+///
+/// The handshake runs on its own thread since a slow player can take seconds to answer, the window keeps being drawn.
+/// Ok(None) if the player gave up, the inner result tells if the connection worked
+fn host(renderer: &mut Renderer, event_pump: &mut EventPump, port: u16, seed: u64, settings: GameSettings) -> Result<Result<Option<NetSession>, String>, String> {
+    let listener = match TcpListener::bind(("0.0.0.0", port)).and_then(|listener| listener.set_nonblocking(true).map(|_| listener)) {
+        Ok(listener) => listener,
+        Err(e) => return Ok(Err(format!("can't listen on port {}: {}", port, e)))
+    };
+    let lines = [format!("Waiting for a player on port {}", port), "Escape to cancel".to_string()];
+
+    let (sender, receiver) = mpsc::channel();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let cancelled_thread = Arc::clone(&cancelled);
+    std::thread::spawn(move || {
+        while !cancelled_thread.load(Ordering::Relaxed) {
+            match NetSession::host(&listener, seed, settings) {
+                Ok(None) => std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32)),
+                connected => {
+                    // Nobody listens anymore if the player gave up during the handshake
+                    let _ = sender.send(connected);
+                    return;
+                }
+            }
+        }
+    });
+
+    loop {
+        for event in event_pump.poll_iter() {
+            if let Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), .. } = event {
+                cancelled.store(true, Ordering::Relaxed);
+                return Ok(Ok(None));
+            }
+        }
+
+        match receiver.try_recv() {
+            Ok(connected) => return Ok(connected),
+            Err(TryRecvError::Empty) => {},
+            Err(TryRecvError::Disconnected) => return Ok(Err("the host thread stopped".to_string()))
+        }
+
+        renderer.draw_page("NETWORK", &lines, None)?;
+        std::thread::sleep(Duration::new(0, 1_000_000_000 / FPS as u32));
+    }
+}
+
+/// Shows a page until the player picks Quit
+fn notice(renderer: &mut Renderer, event_pump: &mut EventPump, title: &str, lines: &[String]) -> Result<(), String> {
+    let mut menu = Menu::new(&["Quit"]);
+
+    loop {
+        match choose(renderer, event_pump, title, lines, &mut menu)? {
+            ControlFlow::Continue(MenuAction::Adjust(..)) => {},
+            _ => return Ok(())
+        }
+    }
+}
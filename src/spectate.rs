@@ -0,0 +1,188 @@
+use std::io::{ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+use crate::board::BUFFER_HEIGHT;
+use crate::game::TetrisGame;
+use crate::tetris::TetrisType;
+
+/// Bytes a viewer can fall behind before it is dropped, a few seconds of play
+const MAX_BACKLOG: usize = 1 << 20;
+
+/// Streams the state of the games to viewers connected over TCP, one JSON object per line, game and tick
+///
+/// Viewers only read, nothing they send is looked at. Slow viewers get dropped instead of slowing the game down.
+/// A line looks like this, rows and positions count from the top of the visible field:
+///
+/// ```json
+/// {"tick":120,"player":0,"mode":"Marathon","width":10,"height":20,"board":[[null,"Line",...],...],
+///  "current_tetris":{"type":"TShape","x":3,"y":-1,"rotation":"Spawn","map":[[false,true,...],...]},
+///  "score":400,"lines":2,"level":1,"gameover":false}
+/// ```
+pub struct Broadcaster {
+    listener: TcpListener,
+    viewers: Vec<Viewer>
+}
+
+struct Viewer {
+    stream: TcpStream,
+    /// Lines waiting for the viewer to read more
+    backlog: Vec<u8>
+}
+
+impl Broadcaster {
+    /// Listens for viewers on the address, like `127.0.0.1:9000`
+    pub fn bind(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address).map_err(|e| format!("can't listen on {}: {}", address, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            listener,
+            viewers: Vec::new()
+        })
+    }
+
+    /// Address viewers connect to, with the port the system picked when binding port 0
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|e| e.to_string())
+    }
+
+    /// Number of viewers connected
+    pub fn viewers(&self) -> usize {
+        self.viewers.len()
+    }
+
+    /// Accepts new viewers and sends them the state of the player's game, call it after every tick.
+    /// Single player games are player 0
+    pub fn broadcast(&mut self, player: usize, game: &TetrisGame) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                let _ = stream.set_nodelay(true);
+                self.viewers.push(Viewer { stream, backlog: Vec::new() });
+            }
+        }
+
+        if self.viewers.is_empty() {
+            return;
+        }
+
+        let mut line = game_json(player, game);
+        line.push('\n');
+        self.viewers.retain_mut(|viewer| {
+            viewer.backlog.extend_from_slice(line.as_bytes());
+            viewer.flush() && viewer.backlog.len() <= MAX_BACKLOG
+        });
+    }
+}
+
+impl Viewer {
+    /// Writes what the socket takes, false once the viewer is gone
+    fn flush(&mut self) -> bool {
+        while !self.backlog.is_empty() {
+            match self.stream.write(&self.backlog) {
+                Ok(0) => return false,
+                Ok(written) => {
+                    self.backlog.drain(..written);
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(_) => return false
+            }
+        }
+
+        true
+    }
+}
+
+/// State of the game after its last tick as a single line of JSON, see [`Broadcaster`]
+pub fn game_json(player: usize, game: &TetrisGame) -> String {
+    let board = game.board();
+    let rows: Vec<String> = board
+        .visible_rows()
+        .map(|row| {
+            let squares: Vec<String> = row
+                .iter()
+                .map(|square| square.map_or("null".to_string(), |tetris_type| format!("\"{}\"", type_name(&tetris_type))))
+                .collect();
+            format!("[{}]", squares.join(","))
+        })
+        .collect();
+
+    let tetris = game.current_tetris();
+    let map: Vec<String> = tetris.map
+        .iter()
+        .map(|row| format!("[{}]", row.map(|filled| filled.to_string()).join(",")))
+        .collect();
+
+    format!(
+        "{{\"tick\":{},\"player\":{},\"mode\":\"{:?}\",\"width\":{},\"height\":{},\"board\":[{}],\
+        \"current_tetris\":{{\"type\":\"{}\",\"x\":{},\"y\":{},\"rotation\":\"{:?}\",\"map\":[{}]}},\
+        \"score\":{},\"lines\":{},\"level\":{},\"gameover\":{}}}",
+        game.ticks(), player, game.settings().mode, board.width(), board.visible_height(), rows.join(","),
+        type_name(&tetris.tetris_type), tetris.position.x, tetris.position.y - BUFFER_HEIGHT as i32, tetris.rotation, map.join(","),
+        game.score(), game.lines(), game.level(), game.is_gameover()
+    )
+}
+
+/// Name of the type in the JSON, custom maps are left out
+fn type_name(tetris_type: &TetrisType) -> String {
+    match tetris_type {
+        TetrisType::Custom(_) => "Custom".to_string(),
+        tetris_type => format!("{:?}", tetris_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Broadcasts until the viewer count is the expected one, the listener may need a moment to see a connection
+    fn broadcast_until(broadcaster: &mut Broadcaster, game: &TetrisGame, viewers: usize) {
+        for _ in 0..500 {
+            broadcaster.broadcast(0, game);
+            if broadcaster.viewers() == viewers {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        panic!("expected {} viewers, got {}", viewers, broadcaster.viewers());
+    }
+
+    #[test]
+    fn json_has_the_whole_state() {
+        let mut game = TetrisGame::new(3);
+        game.tick();
+        let json = game_json(1, &game);
+
+        assert!(json.starts_with("{\"tick\":1,\"player\":1,\"mode\":\"Marathon\",\"width\":10,\"height\":20,\"board\":[["));
+        assert!(json.ends_with(",\"score\":0,\"lines\":0,\"level\":1,\"gameover\":false}"));
+        assert_eq!(json.matches("[null,null,null,null,null,null,null,null,null,null]").count(), 20);
+        assert!(json.contains(&format!("\"current_tetris\":{{\"type\":\"{:?}\"", game.current_tetris().tetris_type)));
+        assert!(!json.contains('\n'));
+    }
+
+    #[test]
+    fn viewers_get_a_line_per_broadcast_and_leave() {
+        let mut broadcaster = Broadcaster::bind("127.0.0.1:0").unwrap();
+        let viewer = TcpStream::connect(broadcaster.local_addr().unwrap()).unwrap();
+        let mut game = TetrisGame::new(8);
+        broadcast_until(&mut broadcaster, &game, 1);
+
+        game.tick();
+        broadcaster.broadcast(0, &game);
+        let mut reader = BufReader::new(viewer);
+        let mut lines = Vec::new();
+        for _ in 0..2 {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            lines.push(line);
+        }
+        assert_eq!(lines[0].trim_end(), game_json(0, &TetrisGame::new(8)));
+        assert_eq!(lines[1].trim_end(), game_json(0, &game));
+
+        drop(reader);
+        broadcast_until(&mut broadcaster, &game, 0);
+    }
+}
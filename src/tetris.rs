@@ -0,0 +1,205 @@
+use crate::rotation::Rotation;
+
+pub const NATURAL_TETRIS: [TetrisType; 7] = [
+            TetrisType::Line,
+            TetrisType::LShapeRight,
+            TetrisType::LShapeLeft,
+            TetrisType::ZShapeRight,
+            TetrisType::ZShapeLeft,
+            TetrisType::TShape,
+            TetrisType::Square,
+];
+
+/// Basic position struct for position handeling
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TetrisType {
+    Line,
+    LShapeRight,
+    LShapeLeft,
+    ZShapeRight,
+    ZShapeLeft,
+    TShape,
+    Square,
+    /// Squares pushed up from the bottom in versus, never falls as a tetris
+    Garbage,
+    Custom(TetrisMap)
+}
+
+/// Map type to make sure tetris management stays consistent
+pub type TetrisMap = [[bool; 5]; 5];
+
+const LINE_MAP: TetrisMap = [[false, false, false, false, false],
+                        [false, false, false, false, false],
+                        [false, true, true, true, true],
+                        [false, false, false, false, false],
+                        [false, false, false, false, false]];
+const LSHAPE_RIGHT_MAP: TetrisMap = [[false, false, false, false, false],
+                                [false, false, false, true, false],
+                                [false, true, true, true, false],
+                                [false, false, false, false, false],
+                                [false, false, false, false, false]];
+const LSHAPE_LEFT_MAP: TetrisMap = [[false, false, false, false, false],
+                                [false, true, false, false, false],
+                                [false, true, true, true, false],
+                                [false, false, false, false, false],
+                                [false, false, false, false, false]];
+const ZSHAPE_RIGHT_MAP: TetrisMap = [[false, false, false, false, false],
+                                [false, true, true, false, false],
+                                [false, false, true, true, false],
+                                [false, false, false, false, false],
+                                [false, false, false, false, false]];
+
+const ZSHAPE_LEFT_MAP: TetrisMap = [[false, false, false, false, false],
+                                [false, false, true, true, false],
+                                [false, true, true, false, false],
+                                [false, false, false, false, false],
+                                [false, false, false, false, false]];
+
+const TSHAPE_MAP: TetrisMap = [[false, false, false, false, false],
+                            [false, false, true, false, false],
+                            [false, true, true, true, false],
+                            [false, false, false, false, false],
+                            [false, false, false, false, false]];
+
+const SQUARE_MAP: TetrisMap = [[false, false, false, false, false],
+                                [false, false, true, true, false],
+                                [false, false, true, true, false],
+                                [false, false, false, false, false],
+                                [false, false, false, false, false]];
+
+
+pub fn tetris_type_to_map(tetris_type: &TetrisType) -> TetrisMap {
+    match tetris_type {
+        TetrisType::Line => LINE_MAP,
+        TetrisType::LShapeRight => LSHAPE_RIGHT_MAP,
+        TetrisType::LShapeLeft => LSHAPE_LEFT_MAP,
+        TetrisType::ZShapeRight => ZSHAPE_RIGHT_MAP,
+        TetrisType::ZShapeLeft => ZSHAPE_LEFT_MAP,
+        TetrisType::TShape => TSHAPE_MAP,
+        TetrisType::Square => SQUARE_MAP,
+        TetrisType::Garbage | TetrisType::Custom(_) => [[false; 5]; 5] // Return an empty map
+    }
+}
+
+/// Contains the tetris props and transform logics
+#[derive(Clone, Copy, Debug)]
+pub struct Tetris {
+    /// Current tetris position
+    pub position: Position,
+    /// Current tetris type
+    pub tetris_type: TetrisType,
+    /// Contains the tetris shape
+    pub map: TetrisMap,
+    /// Number of turns from the spawn orientation
+    pub rotation: Rotation
+}
+
+impl Tetris {
+    /// Makes a new tetris object in its spawn orientation, at the top left of the board
+    /// Map is derived from the type.
+    /// If type is [`TetrisType::Custom`] it will use the map within the Enum
+    pub fn new(tetris_type: TetrisType) -> Self {
+        let map = if let TetrisType::Custom(map) = tetris_type {
+            map
+        } else {
+            tetris_type_to_map(&tetris_type)
+        };
+
+        Self {
+            position: Position { x: 0, y: 0 },
+            tetris_type,
+            map,
+            rotation: Rotation::Spawn
+        }
+    }
+
+    /// Return the tetris map if it was rotated right
+    pub fn rotate_right_result(&self) -> TetrisMap {
+        let mut new_tetris: TetrisMap = [[false; 5]; 5];
+
+        for (yindex, y) in self.map.iter().enumerate() {
+            for (xindex, x) in y.iter().enumerate() {
+                new_tetris[xindex][4 - yindex] = *x;
+            }
+        }
+
+        new_tetris
+    }
+
+    /// Return the tetris map if it was rotated left
+    pub fn rotate_left_result(&self) -> TetrisMap {
+        let mut new_tetris: TetrisMap = [[false; 5]; 5];
+
+        for (yindex, y) in self.map.iter().enumerate() {
+            for (xindex, x) in y.iter().enumerate() {
+                new_tetris[4 - xindex][yindex] = *x;
+            }
+        }
+
+        new_tetris
+    }
+
+    /// Rotates the tetris map right directly
+    pub fn rotate_right(&mut self) {
+        self.map = self.rotate_right_result();
+        self.rotation = self.rotation.clockwise();
+    }
+
+    /// Rotates the tetris map left directly
+    pub fn rotate_left(&mut self) {
+        self.map = self.rotate_left_result();
+        self.rotation = self.rotation.counter_clockwise();
+    }
+
+    /// Finds the most left square in map
+    ///
+    /// returns the x index of it
+    pub fn most_left(&self) -> Option<usize> {
+        let mut pos: Option<usize> = None;
+        for y in self.map.iter() {
+            for (xindex, x) in y.iter().enumerate() {
+                if *x && xindex < pos.unwrap_or(200) {
+                    pos = Some(xindex);
+                }
+            }
+        }
+
+        pos
+    }
+
+    /// Finds the most right square in map
+    ///
+    /// returns the x index of it
+    pub fn most_right(&self) -> Option<usize> {
+        let mut pos: Option<usize> = None;
+        for y in self.map.iter() {
+            for (xindex, x) in y.iter().enumerate() {
+                if *x && xindex > pos.unwrap_or(0) {
+                    pos = Some(xindex);
+                }
+            }
+        }
+
+        pos
+    }
+
+    /// Finds the most bottom square in map
+    ///
+    /// returns the y index of it
+    pub fn most_bottom(&self) -> Option<usize> {
+        let mut pos: Option<usize> = None;
+        for (yindex, y) in self.map.iter().enumerate() {
+            if y.contains(&true) {
+                pos = Some(yindex);
+            }
+        }
+
+        pos
+    }
+}
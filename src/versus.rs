@@ -0,0 +1,174 @@
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::game::{GameEvent, GameMode, GameSettings, Input, TetrisGame};
+
+/// Number of players in a versus match
+pub const PLAYERS: usize = 2;
+/// Mixed into the match seed for the garbage holes so they don't follow the tetris order
+const GARBAGE_SEED_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// How a versus match ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersusResult {
+    /// Index of the player still standing
+    Winner(usize),
+    /// Both players topped out on the same tick
+    Draw
+}
+
+/// Two games side by side, line clears send garbage to the opponent
+///
+/// Both players get the same tetris order. The match is always played in marathon
+/// and ends as soon as someone tops out
+pub struct Versus {
+    games: [TetrisGame; PLAYERS],
+    /// Picks the hole column of the garbage, seeded with the match so it can be replayed
+    rng: SmallRng,
+    /// Events of each game since the last drain, the garbage ones were already handled
+    events: [Vec<GameEvent>; PLAYERS],
+    /// Garbage lines each player sent, cancelled ones not counted
+    garbage_sent: [usize; PLAYERS],
+    result: Option<VersusResult>
+}
+
+impl Versus {
+    pub fn new(seed: u64, settings: GameSettings) -> Self {
+        let settings = GameSettings { mode: GameMode::Marathon, ..settings };
+
+        Self {
+            games: [TetrisGame::with_settings(seed, settings), TetrisGame::with_settings(seed, settings)],
+            rng: SmallRng::seed_from_u64(seed ^ GARBAGE_SEED_SALT),
+            events: [Vec::new(), Vec::new()],
+            garbage_sent: [0; PLAYERS],
+            result: None
+        }
+    }
+
+    /// Game of the player, 0 or 1
+    pub fn game(&self, player: usize) -> &TetrisGame {
+        &self.games[player]
+    }
+
+    /// Seed both games were created with
+    pub fn seed(&self) -> u64 {
+        self.games[0].seed()
+    }
+
+    pub fn garbage_sent(&self, player: usize) -> usize {
+        self.garbage_sent[player]
+    }
+
+    /// None while the match goes on
+    pub fn result(&self) -> Option<VersusResult> {
+        self.result
+    }
+
+    /// Applies an action of the player, refused once the match is over
+    pub fn handle_input(&mut self, player: usize, input: Input) -> bool {
+        if self.result.is_some() {
+            return false;
+        }
+
+        self.games[player].handle_input(input)
+    }
+
+    /// Advances both games by one frame, then sends the garbage of their line clears to the opponent
+    pub fn tick(&mut self) {
+        if self.result.is_some() {
+            return;
+        }
+
+        for game in self.games.iter_mut() {
+            game.tick();
+        }
+
+        for player in 0..PLAYERS {
+            let events = self.games[player].drain_events();
+            for event in &events {
+                if let GameEvent::GarbageSent(lines) = event {
+                    let opponent = &mut self.games[PLAYERS - 1 - player];
+                    let hole = self.rng.random_range(0..opponent.board().width());
+                    opponent.receive_garbage(*lines, hole);
+                    self.garbage_sent[player] += lines;
+                }
+            }
+            self.events[player].extend(events);
+        }
+
+        self.result = match (self.games[0].is_gameover(), self.games[1].is_gameover()) {
+            (true, true) => Some(VersusResult::Draw),
+            (true, false) => Some(VersusResult::Winner(1)),
+            (false, true) => Some(VersusResult::Winner(0)),
+            (false, false) => None
+        };
+    }
+
+    /// Takes every event of the player's game since the last call
+    pub fn drain_events(&mut self, player: usize) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events[player])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ai::Ai;
+
+    use super::*;
+
+    /// Player 0 is the AI, player 1 hard drops every tetris in the middle and tops out first
+    fn ai_against_stacker(seed: u64) -> Versus {
+        let mut versus = Versus::new(seed, GameSettings::default());
+        let mut ai = Ai::default();
+
+        while versus.result().is_none() {
+            for _ in 0..64 {
+                let Some(input) = ai.next_input(versus.game(0)) else { break };
+                versus.handle_input(0, input);
+                if input == Input::HardDrop {
+                    break;
+                }
+            }
+            versus.handle_input(1, Input::HardDrop);
+            versus.tick();
+        }
+
+        versus
+    }
+
+    #[test]
+    fn both_players_get_the_same_tetris() {
+        let mut versus = Versus::new(4, GameSettings { mode: GameMode::Sprint, ..Default::default() });
+        assert_eq!(versus.game(0).settings().mode, GameMode::Marathon);
+
+        for _ in 0..20 {
+            assert_eq!(versus.game(0).current_tetris().tetris_type, versus.game(1).current_tetris().tetris_type);
+            for player in 0..PLAYERS {
+                versus.handle_input(player, Input::HardDrop);
+            }
+            versus.tick();
+        }
+    }
+
+    #[test]
+    fn stacker_loses_and_the_match_stops() {
+        let mut versus = ai_against_stacker(8);
+        assert_eq!(versus.result(), Some(VersusResult::Winner(0)));
+        assert_eq!(versus.garbage_sent(1), 0);
+
+        let ticks = versus.game(0).ticks();
+        assert!(!versus.handle_input(0, Input::MoveLeft));
+        versus.tick();
+        assert_eq!(versus.game(0).ticks(), ticks);
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_match() {
+        let first = ai_against_stacker(21);
+        let second = ai_against_stacker(21);
+        for player in 0..PLAYERS {
+            assert_eq!(first.game(player).board(), second.game(player).board());
+            assert_eq!(first.garbage_sent(player), second.garbage_sent(player));
+        }
+    }
+}